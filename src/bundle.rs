@@ -0,0 +1,479 @@
+use crate::{error::KahError::CircularImport, languages::Languages};
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    fs::{canonicalize, read_to_string},
+    path::{Path, PathBuf},
+};
+
+/// A solution with its local modules inlined, ready to be submitted as a
+/// single file.
+#[derive(Debug)]
+pub(crate) struct Bundle {
+    pub(crate) code: String,
+    /// Every file in the bundle, the solution first
+    pub(crate) files: Vec<PathBuf>,
+}
+
+impl Bundle {
+    /// Whether any modules were inlined.
+    pub(crate) fn is_bundled(&self) -> bool {
+        self.files.len() > 1
+    }
+}
+
+/// Inlines the local `#include "..."`s of C++, `mod foo;`s of Rust and
+/// imports of Python modules next to the solution. Other languages and
+/// solutions without local modules are returned as they are.
+pub(crate) fn bundle(file: &Path, language: &Languages) -> Result<Bundle> {
+    let mut bundler = Bundler {
+        root: file.parent().unwrap_or_else(|| Path::new(".")).to_owned(),
+        seen: HashSet::new(),
+        inlined: HashSet::new(),
+        importing: Vec::new(),
+        files: Vec::new(),
+    };
+
+    let code = match language {
+        Languages::CPP => bundler.cpp(file)?,
+        Languages::Rust => bundler.rust(file, true)?,
+        Languages::Python => bundler.python(file, false)?,
+        _ => bundler.read(file)?,
+    };
+
+    Ok(Bundle {
+        code,
+        files: bundler.files,
+    })
+}
+
+#[derive(Debug)]
+struct Bundler {
+    /// Directory of the solution, where Python looks for modules
+    root: PathBuf,
+    seen: HashSet<PathBuf>,
+    /// Python modules inlined at the top level by `from` imports
+    inlined: HashSet<PathBuf>,
+    /// Python modules being bundled, innermost last
+    importing: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+impl Bundler {
+    fn read(&mut self, file: &Path) -> Result<String> {
+        if self.seen.insert(canonicalize(file)?) {
+            self.files.push(file.to_owned());
+        }
+
+        Ok(read_to_string(file)?)
+    }
+
+    fn is_seen(&self, file: &Path) -> bool {
+        canonicalize(file).is_ok_and(|f| self.seen.contains(&f))
+    }
+
+    /// Every file is included once, as if it had an include guard.
+    fn cpp(&mut self, file: &Path) -> Result<String> {
+        let dir = file.parent().unwrap_or_else(|| Path::new("."));
+        let source = self.read(file)?;
+        let mut code = String::with_capacity(source.len());
+
+        for line in source.lines() {
+            let trimmed = line.trim();
+            if trimmed == "#pragma once" {
+                continue;
+            }
+
+            match local_include(trimmed).map(|i| dir.join(i)) {
+                Some(include) if include.is_file() => {
+                    if !self.is_seen(&include) {
+                        code.push_str(&self.cpp(&include)?);
+                    }
+                }
+                _ => {
+                    code.push_str(line);
+                    code.push('\n');
+                }
+            }
+        }
+
+        Ok(code)
+    }
+
+    /// Replaces `mod foo;` with `mod foo { ... }`, looking for `foo.rs` or
+    /// `foo/mod.rs` like rustc does. Modules only compiled for tests are
+    /// left out.
+    fn rust(&mut self, file: &Path, owns_dir: bool) -> Result<String> {
+        let dir = file.parent().unwrap_or_else(|| Path::new("."));
+        let dir = match file.file_stem() {
+            Some(stem) if !owns_dir => dir.join(stem),
+            _ => dir.to_owned(),
+        };
+        let source = self.read(file)?;
+        let mut code = String::with_capacity(source.len());
+        let mut lines = source.lines().peekable();
+
+        while let Some(line) = lines.next() {
+            if line.trim() == "#[cfg(test)]" && lines.peek().is_some_and(|l| is_mod(l.trim())) {
+                skip_item(&mut lines);
+                continue;
+            }
+
+            let module = match module_declaration(line.trim()) {
+                Some(module) => module,
+                None => {
+                    code.push_str(line);
+                    code.push('\n');
+                    continue;
+                }
+            };
+
+            let candidates = [
+                (dir.join(format!("{}.rs", module.name)), false),
+                (dir.join(module.name).join("mod.rs"), true),
+            ];
+            match candidates.iter().find(|(path, _)| path.is_file()) {
+                Some((path, owns_dir)) if !self.is_seen(path) => {
+                    let indent = &line[..line.len() - line.trim_start().len()];
+                    code.push_str(&format!(
+                        "{}{}mod {} {{\n",
+                        indent, module.visibility, module.name
+                    ));
+                    code.push_str(&self.rust(path, *owns_dir)?);
+                    code.push_str(indent);
+                    code.push_str("}\n");
+                }
+                _ => {
+                    code.push_str(line);
+                    code.push('\n');
+                }
+            }
+        }
+
+        Ok(code)
+    }
+
+    /// Inlines `from foo import ...` in place, while `import foo` gets a
+    /// module object so `foo.bar` keeps working. Only modules next to the
+    /// solution are inlined, and their `if __name__ == "__main__":` blocks
+    /// are left out. Modules that import each other cannot be bundled.
+    fn python(&mut self, file: &Path, is_module: bool) -> Result<String> {
+        let canonical = canonicalize(file)?;
+        if self.importing.contains(&canonical) {
+            return Err(CircularImport(file.display().to_string()).into());
+        }
+
+        self.importing.push(canonical);
+        let code = self.python_module(file, is_module);
+        self.importing.pop();
+
+        code
+    }
+
+    fn python_module(&mut self, file: &Path, is_module: bool) -> Result<String> {
+        let source = self.read(file)?;
+        let mut code = String::with_capacity(source.len());
+        let mut lines = source.lines().peekable();
+
+        while let Some(line) = lines.next() {
+            if is_module && is_main_guard(line) {
+                while lines
+                    .peek()
+                    .is_some_and(|l| l.trim().is_empty() || l.starts_with(char::is_whitespace))
+                {
+                    lines.next();
+                }
+                continue;
+            }
+
+            let import = match python_import(line) {
+                Some(import) => import,
+                None => {
+                    code.push_str(line);
+                    code.push('\n');
+                    continue;
+                }
+            };
+
+            let path = self
+                .root
+                .join(format!("{}.py", import.module.replace('.', "/")));
+            if !path.is_file() {
+                code.push_str(line);
+                code.push('\n');
+                continue;
+            }
+
+            match import.names {
+                Some(names) => {
+                    if self.inlined.insert(canonicalize(&path)?) {
+                        code.push_str(&self.python(&path, true)?);
+                    }
+                    for (name, alias) in names {
+                        if name != alias {
+                            code.push_str(&format!("{} = {}\n", alias, name));
+                        }
+                    }
+                }
+                None => {
+                    let module = self.python(&path, true)?;
+                    code.push_str(&format!(
+                        "{alias} = __import__(\"types\").ModuleType({name})\n\
+                         exec({source}, {alias}.__dict__)\n",
+                        alias = import.alias,
+                        name = python_string(&import.module),
+                        source = python_string(&module)
+                    ));
+                }
+            }
+        }
+
+        Ok(code)
+    }
+}
+
+/// The file in `#include "file"`, but not in `#include <file>`.
+fn local_include(line: &str) -> Option<&str> {
+    let rest = line
+        .strip_prefix('#')?
+        .trim_start()
+        .strip_prefix("include")?;
+    let rest = rest.trim_start().strip_prefix('"')?;
+    let (file, _) = rest.split_once('"')?;
+
+    Some(file)
+}
+
+#[derive(Debug, PartialEq)]
+struct ModuleDeclaration<'a> {
+    visibility: &'a str,
+    name: &'a str,
+}
+
+/// Parses `mod foo;` and `pub(crate) mod foo;`.
+fn module_declaration(line: &str) -> Option<ModuleDeclaration<'_>> {
+    let declaration = line.strip_suffix(';')?.trim_end();
+    let (visibility, rest) = match declaration.find("mod ") {
+        Some(index) => declaration.split_at(index),
+        None => return None,
+    };
+    if !(visibility.is_empty() || visibility.starts_with("pub")) {
+        return None;
+    }
+
+    let name = rest.strip_prefix("mod ")?.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some(ModuleDeclaration { visibility, name })
+}
+
+fn is_mod(line: &str) -> bool {
+    let line = line.trim_start_matches("pub ").trim_start();
+    line.starts_with("mod ")
+}
+
+/// Skips a `mod foo;` or `mod foo { ... }`, counting braces to find where the
+/// block ends.
+fn skip_item<'a, I: Iterator<Item = &'a str>>(lines: &mut I) {
+    let mut depth = 0;
+    for line in lines {
+        depth += line.matches('{').count() as i64 - line.matches('}').count() as i64;
+        if depth <= 0 && (line.contains('}') || line.trim_end().ends_with(';')) {
+            return;
+        }
+    }
+}
+
+fn python_string(text: &str) -> String {
+    let mut string = String::with_capacity(text.len() + 2);
+    string.push('"');
+    for c in text.chars() {
+        match c {
+            '\\' => string.push_str("\\\\"),
+            '"' => string.push_str("\\\""),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+
+    string
+}
+
+fn is_main_guard(line: &str) -> bool {
+    let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    line == "if__name__==\"__main__\":" || line == "if__name__=='__main__':"
+}
+
+#[derive(Debug, PartialEq)]
+struct PythonImport {
+    module: String,
+    alias: String,
+    /// The names and aliases of a `from` import
+    names: Option<Vec<(String, String)>>,
+}
+
+/// Parses top level `import foo`, `import foo as bar` and
+/// `from foo import a, b as c` of a single module.
+fn python_import(line: &str) -> Option<PythonImport> {
+    let line = line.split('#').next()?.trim_end();
+
+    if let Some(rest) = line.strip_prefix("import ") {
+        if rest.contains(',') {
+            return None;
+        }
+        let (module, alias) = with_alias(rest)?;
+        // `import a.b` would need a module for `a` as well
+        if alias.contains('.') {
+            return None;
+        }
+        return Some(PythonImport {
+            module,
+            alias,
+            names: None,
+        });
+    }
+
+    let rest = line.strip_prefix("from ")?;
+    let (module, names) = rest.split_once(" import ")?;
+    let names = names.trim().trim_start_matches('(').trim_end_matches(')');
+    let names = if names.trim() == "*" {
+        Vec::new()
+    } else {
+        names
+            .split(',')
+            .filter(|n| !n.trim().is_empty())
+            .map(with_alias)
+            .collect::<Option<_>>()?
+    };
+
+    Some(PythonImport {
+        module: module.trim().to_string(),
+        alias: module.trim().to_string(),
+        names: Some(names),
+    })
+}
+
+fn with_alias(name: &str) -> Option<(String, String)> {
+    let mut parts = name.split_whitespace();
+    let name = parts.next()?.to_string();
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (None, _, _) => Some((name.clone(), name)),
+        (Some("as"), Some(alias), None) => Some((name, alias.to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(dir: &Path, file: &str, content: &str) -> PathBuf {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn bundles_cpp_includes_once() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "lib/dsu.h", "#pragma once\nstruct Dsu {};\n");
+        write(
+            dir.path(),
+            "lib/graph.h",
+            "#pragma once\n#include \"dsu.h\"\nstruct Graph {};\n",
+        );
+        let main = write(
+            dir.path(),
+            "main.cpp",
+            "#include <vector>\n#include \"lib/dsu.h\"\n#include \"lib/graph.h\"\nint main() {}\n",
+        );
+
+        let bundle = bundle(&main, &Languages::CPP).unwrap();
+        assert_eq!(
+            bundle.code,
+            "#include <vector>\nstruct Dsu {};\nstruct Graph {};\nint main() {}\n"
+        );
+        assert_eq!(bundle.files.len(), 3);
+    }
+
+    #[test]
+    fn bundles_rust_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "io.rs", "pub mod scanner;\n");
+        write(dir.path(), "io/scanner.rs", "pub struct Scanner;\n");
+        write(dir.path(), "tests.rs", "#[test]\nfn it_works() {}\n");
+        let main = write(
+            dir.path(),
+            "main.rs",
+            "mod io;\n#[cfg(test)]\nmod tests;\n#[cfg(test)]\nmod more {\n    fn f() {}\n}\nfn main() {}\n",
+        );
+
+        let bundle = bundle(&main, &Languages::Rust).unwrap();
+        assert_eq!(
+            bundle.code,
+            "mod io {\npub mod scanner {\npub struct Scanner;\n}\n}\nfn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn bundles_python_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "util.py",
+            "def read_ints():\n    return [1]\n\nif __name__ == \"__main__\":\n    print(read_ints())\n",
+        );
+        let main = write(
+            dir.path(),
+            "main.py",
+            "import sys\nimport util\nfrom util import read_ints as ints\nprint(ints())\n",
+        );
+
+        let bundle = bundle(&main, &Languages::Python).unwrap();
+        assert_eq!(
+            bundle.code,
+            "import sys\n\
+             util = __import__(\"types\").ModuleType(\"util\")\n\
+             exec(\"def read_ints():\\n    return [1]\\n\\n\", util.__dict__)\n\
+             def read_ints():\n    return [1]\n\nints = read_ints\n\
+             print(ints())\n"
+        );
+    }
+
+    #[test]
+    fn rejects_circular_python_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.py", "import b\nX = 1\n");
+        write(dir.path(), "b.py", "import a\nY = a.X\n");
+        let main = write(dir.path(), "main.py", "import a\nprint(a.X)\n");
+
+        let error = bundle(&main, &Languages::Python).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(crate::error::KahError::CircularImport(file)) if file.ends_with("a.py")
+        ));
+    }
+
+    #[test]
+    fn parses_declarations() {
+        assert_eq!(local_include("#include \"a.h\" // x"), Some("a.h"));
+        assert_eq!(local_include("#include <a.h>"), None);
+        assert_eq!(
+            module_declaration("pub(crate) mod io;"),
+            Some(ModuleDeclaration {
+                visibility: "pub(crate) ",
+                name: "io"
+            })
+        );
+        assert_eq!(module_declaration("mod io {"), None);
+        assert_eq!(python_import("import a, b"), None);
+    }
+}
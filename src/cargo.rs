@@ -0,0 +1,238 @@
+use crate::layout::ProblemPaths;
+use anyhow::Result;
+use std::{
+    fs,
+    fs::read_to_string,
+    path::{Component, Path, PathBuf},
+};
+use toml_edit::{Array, DocumentMut, Item};
+
+/// The Rust edition Kattis compiles solutions with.
+pub(crate) const KATTIS_EDITION: &str = "2021";
+
+/// Moves the solution into a cargo package as its `src/main.rs`. The package
+/// is the folder of the solution if it is a `main.rs` already, otherwise a
+/// folder named after the solution, e.g. `rust/HelloWorld/src/main.rs`.
+pub(crate) fn in_package(paths: ProblemPaths) -> ProblemPaths {
+    let solution = &paths.solution;
+    let package = if solution.file_stem().is_some_and(|s| s == "main") {
+        solution.parent().map(Path::to_owned).unwrap_or_default()
+    } else {
+        solution.with_extension("")
+    };
+
+    ProblemPaths {
+        solution: package.join("src").join("main.rs"),
+        ..paths
+    }
+}
+
+/// The manifest of the package a solution is the `src/main.rs` of.
+pub(crate) fn manifest(solution: &Path) -> Option<PathBuf> {
+    if solution.file_name()? != "main.rs" {
+        return None;
+    }
+    let src = solution.parent()?;
+    if src.file_name()? != "src" {
+        return None;
+    }
+
+    Some(src.parent()?.join("Cargo.toml")).filter(|m| m.is_file())
+}
+
+/// Writes the manifest of the package of a solution unless it has one. The
+/// package has no dependencies since Kattis does not allow any, and becomes
+/// a member of the cargo workspace it is in, if any.
+pub(crate) fn create_package(solution: &Path) -> Result<()> {
+    let dir = match solution.parent().and_then(Path::parent) {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    let manifest = dir.join("Cargo.toml");
+    if manifest.exists() {
+        return Ok(());
+    }
+
+    let workspace = match find_workspace(dir)? {
+        Some(workspace) => {
+            add_member(&workspace, dir)?;
+            ""
+        }
+        None => "\n# Not part of any other cargo workspace\n[workspace]\n",
+    };
+    let name = package_name(dir);
+    fs::write(
+        manifest,
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"{}\"\npublish = false\n\n\
+             # Kattis does not allow dependencies\n[dependencies]\n{}",
+            name, KATTIS_EDITION, workspace
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Package names may only have letters, numbers, `-` and `_`.
+fn package_name(dir: &Path) -> String {
+    let name: String = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '_' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect();
+
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        _ => format!("problem_{}", name),
+    }
+}
+
+/// Finds the manifest of the cargo workspace a directory is in, like cargo
+/// does.
+fn find_workspace(dir: &Path) -> Result<Option<PathBuf>> {
+    for dir in dir.ancestors().skip(1) {
+        let manifest = dir.join("Cargo.toml");
+        if manifest.is_file() {
+            let value: toml::Value = toml::from_str(&read_to_string(&manifest)?)?;
+            if value.get("workspace").is_some() {
+                return Ok(Some(manifest));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Adds a package to the members of a workspace, unless a member already
+/// covers it like `rust/*` does. The manifest is the user's, so it is edited
+/// in place to keep its comments and formatting.
+fn add_member(workspace: &Path, package: &Path) -> Result<()> {
+    let root = workspace.parent().unwrap_or_else(|| Path::new("."));
+    let relative = match package.strip_prefix(root) {
+        Ok(relative) => relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => return Ok(()),
+    };
+
+    let mut document: DocumentMut = read_to_string(workspace)?.parse()?;
+    let members = document
+        .get_mut("workspace")
+        .and_then(Item::as_table_like_mut)
+        .map(|w| w.entry("members").or_insert(toml_edit::value(Array::new())))
+        .and_then(Item::as_array_mut);
+    let members = match members {
+        Some(members) => members,
+        None => return Ok(()),
+    };
+
+    let covered = members
+        .iter()
+        .filter_map(|m| m.as_str())
+        .any(|m| is_member(m, &relative));
+    if !covered {
+        members.push(relative);
+        fs::write(workspace, document.to_string())?;
+    }
+
+    Ok(())
+}
+
+fn is_member(member: &str, package: &str) -> bool {
+    let parent = package.rsplit_once('/').map_or("", |(parent, _)| parent);
+
+    match member.trim_end_matches('/').strip_suffix('*') {
+        Some(prefix) => parent == prefix.trim_end_matches('/'),
+        None => member.trim_end_matches('/') == package,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(solution: &str) -> ProblemPaths {
+        ProblemPaths {
+            solution: PathBuf::from(solution),
+            statement: PathBuf::from("statement.md"),
+            notes: None,
+            tests: None,
+        }
+    }
+
+    #[test]
+    fn moves_solutions_into_packages() {
+        assert_eq!(
+            in_package(paths("rust/HelloWorld.rs")).solution,
+            PathBuf::from("rust/HelloWorld/src/main.rs")
+        );
+        assert_eq!(
+            in_package(paths("hello/main.rs")).solution,
+            PathBuf::from("hello/src/main.rs")
+        );
+        assert_eq!(
+            package_name(Path::new("rust/A_Hello World")),
+            "a_hello_world"
+        );
+        assert_eq!(package_name(Path::new("3dprinter")), "problem_3dprinter");
+    }
+
+    #[test]
+    fn creates_workspace_members() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"lib\"]\n",
+        )
+        .unwrap();
+        let solution = dir.path().join("rust/HelloWorld/src/main.rs");
+        fs::create_dir_all(solution.parent().unwrap()).unwrap();
+
+        create_package(&solution).unwrap();
+        let package = read_to_string(dir.path().join("rust/HelloWorld/Cargo.toml")).unwrap();
+        assert!(package.contains("name = \"helloworld\""));
+        assert!(package.contains(&format!("edition = \"{}\"", KATTIS_EDITION)));
+        assert!(!package.contains("[workspace]"));
+        assert_eq!(
+            manifest(&solution),
+            Some(dir.path().join("rust/HelloWorld/Cargo.toml"))
+        );
+
+        assert_eq!(
+            read_to_string(dir.path().join("Cargo.toml")).unwrap(),
+            "[workspace]\nmembers = [\"lib\", \"rust/HelloWorld\"]\n"
+        );
+        assert!(is_member("rust/*", "rust/HelloWorld"));
+        assert!(!is_member("rust/*", "contests/abc/rust/A_Hello"));
+    }
+
+    #[test]
+    fn keeps_workspace_formatting() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "# Kattis solutions\n[profile.release]\nlto = true\n\n\
+             [workspace]\nresolver = \"2\" # needed for edition 2021\n",
+        )
+        .unwrap();
+
+        add_member(&manifest, &dir.path().join("hello")).unwrap();
+        assert_eq!(
+            read_to_string(&manifest).unwrap(),
+            "# Kattis solutions\n[profile.release]\nlto = true\n\n\
+             [workspace]\nresolver = \"2\" # needed for edition 2021\nmembers = [\"hello\"]\n"
+        );
+    }
+}
@@ -0,0 +1,656 @@
+use crate::{
+    error::KahError::{FetchError, LoginError, NoToken, NotCached, Offline},
+    http::{CacheEntry, HttpCache},
+    kah::Kattis,
+    secret::{Secret, SecretStore},
+};
+use anyhow::Result;
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header::{
+        HeaderMap, CONTENT_TYPE, COOKIE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        RETRY_AFTER, SET_COOKIE,
+    },
+    StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const USER_AGENT: &str = concat!("kah/", env!("CARGO_PKG_VERSION"));
+
+/// Longest we wait between two attempts of a request.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// When a request may be sent again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Retry {
+    /// Requests that do the same every time, retried on timeouts, connection
+    /// errors, rate limiting and server errors
+    Idempotent,
+    /// Requests like submissions that Kattis may have acted on even if we
+    /// never see the response, only retried when rate limited or when they
+    /// never went out
+    Unsent,
+}
+
+/// Settings for talking to Kattis, stored in the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ClientConfig {
+    /// Seconds before a request times out
+    #[serde(default = "default_timeout")]
+    pub(crate) timeout: u64,
+    /// How many times a request is retried when rate limited or Kattis fails
+    #[serde(default = "default_retries")]
+    pub(crate) retries: u32,
+    /// Use this URL instead of the hostname from the kattisrc
+    #[serde(default)]
+    pub(crate) base_url: Option<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            timeout: default_timeout(),
+            retries: default_retries(),
+            base_url: None,
+        }
+    }
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+/// The single HTTP client used to talk to Kattis. Responses to `get` are
+/// cached, and cookies are kept between runs so we stay logged in.
+#[derive(Debug, Default)]
+pub(crate) struct KattisClient {
+    client: Client,
+    base_url: String,
+    /// Host of the Kattis instance, absolute URLs on it go to `base_url`
+    host: Option<String>,
+    username: String,
+    token: Secret,
+    /// Where to find the token when it is not given directly
+    secrets: Option<(Box<dyn SecretStore>, String)>,
+    retries: u32,
+    offline: bool,
+    cache: HttpCache,
+    cookie_file: PathBuf,
+    cookies: RefCell<BTreeMap<String, String>>,
+}
+
+impl KattisClient {
+    pub(crate) fn new(
+        kattis: &Kattis,
+        config: &ClientConfig,
+        dir: &Path,
+        offline: bool,
+    ) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(config.timeout))
+            .build()?;
+
+        let cookie_file = dir.join("cookies.json");
+        let cookies = match read_to_string(&cookie_file) {
+            Ok(cookies) => serde_json::from_str(&cookies).unwrap_or_default(),
+            Err(_) => BTreeMap::new(),
+        };
+
+        let base_url = config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| kattis.hostname.clone());
+
+        Ok(KattisClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            host: Url::parse(&kattis.hostname)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string)),
+            username: kattis.username.clone(),
+            token: kattis.token.clone(),
+            secrets: None,
+            retries: config.retries,
+            offline,
+            cache: HttpCache::new(&dir.join("cache")),
+            cookie_file,
+            cookies: RefCell::new(cookies),
+        })
+    }
+
+    /// Reads the token from a secret store when it is needed to log in.
+    pub(crate) fn with_secrets(mut self, secrets: Box<dyn SecretStore>, key: String) -> Self {
+        self.secrets = Some((secrets, key));
+        self
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Turns a path like `/problems/hello` into a full URL. Full URLs on the
+    /// Kattis host, like the submission URL from the kattisrc, are moved to
+    /// the base URL so they follow its override too, other ones are left as
+    /// they are.
+    pub(crate) fn url(&self, path: &str) -> String {
+        let path = match Url::parse(path) {
+            Ok(url) if url.host_str().is_some() && url.host_str() == self.host.as_deref() => {
+                match url.query() {
+                    Some(query) => format!("{}?{}", url.path(), query),
+                    None => url.path().to_string(),
+                }
+            }
+            Ok(_) => return path.to_string(),
+            Err(_) => path.to_string(),
+        };
+
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// Fetches a page or file, revalidating any cached copy with `ETag` and
+    /// `Last-Modified`, or only using the cache when offline.
+    pub(crate) fn get(&self, path: &str) -> Result<Vec<u8>> {
+        let url = self.url(path);
+        let cached = self.cache.get(&url);
+
+        if self.offline {
+            return match cached {
+                Some((_, body)) => Ok(body),
+                None => Err(NotCached(url).into()),
+            };
+        }
+
+        let response = self.send(Retry::Idempotent, || {
+            let mut request = self.client.get(&url);
+            if let Some((entry, _)) = &cached {
+                if let Some(etag) = &entry.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            request
+        })?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some((_, body)) = cached {
+                return Ok(body);
+            }
+        }
+
+        let response = match response.error_for_status() {
+            Ok(response) => response,
+            Err(err) => return Err(FetchError(url, err.to_string()).into()),
+        };
+
+        let entry = CacheEntry {
+            url,
+            etag: header(response.headers(), ETAG),
+            last_modified: header(response.headers(), LAST_MODIFIED),
+        };
+        let body = response.bytes()?.to_vec();
+        self.cache.store(&entry, &body)?;
+
+        Ok(body)
+    }
+
+    pub(crate) fn get_text(&self, path: &str) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.get(path)?).into_owned())
+    }
+
+    /// Logs in with the token from the kattisrc, the session cookie is kept
+    /// for later requests.
+    pub(crate) fn login(&self) -> Result<()> {
+        let token = self.token()?;
+        self.post_login(&self.username, ("token", token.expose()))
+    }
+
+    fn token(&self) -> Result<Secret> {
+        if !self.token.is_empty() {
+            return Ok(self.token.clone());
+        }
+
+        match &self.secrets {
+            Some((secrets, key)) => secrets.get(key)?.ok_or_else(|| NoToken(key.clone()).into()),
+            None => Err(NoToken(self.username.clone()).into()),
+        }
+    }
+
+    /// Logs in with a username and password instead of a token, e.g. to
+    /// download the kattisrc.
+    pub(crate) fn login_with_password(&self, username: &str, password: &str) -> Result<()> {
+        self.post_login(username, ("password", password))
+    }
+
+    fn post_login(&self, username: &str, secret: (&str, &str)) -> Result<()> {
+        if self.offline {
+            return Err(Offline("log in".to_string()).into());
+        }
+
+        let url = self.url("/login");
+        let response = self.send(Retry::Unsent, || {
+            self.client
+                .post(&url)
+                .form(&[("user", username), secret, ("script", "true")])
+        })?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(LoginError(username.to_string(), status.to_string()).into()),
+        }
+    }
+
+    /// Downloads the kattisrc of the user we are logged in as. It holds the
+    /// token, so it is never cached.
+    pub(crate) fn download_kattisrc(&self) -> Result<String> {
+        if self.offline {
+            return Err(Offline("download the kattisrc".to_string()).into());
+        }
+
+        let url = self.url("/download/kattisrc");
+        let response = self.send(Retry::Idempotent, || self.client.get(&url))?;
+        let body = match response.error_for_status() {
+            Ok(response) => response.text()?,
+            Err(err) => return Err(FetchError(url, err.to_string()).into()),
+        };
+
+        // Kattis sends us to the login page when we are not logged in
+        if !body.contains("[user]") {
+            return Err(FetchError(url, "the response is not a kattisrc".to_string()).into());
+        }
+
+        Ok(body)
+    }
+
+    /// Posts a form with a file as `multipart/form-data`, returning the body of
+    /// the response. Used for submissions, which are never cached.
+    pub(crate) fn post_file(
+        &self,
+        path: &str,
+        fields: &[(&str, &str)],
+        file: (&str, &str, &[u8]),
+    ) -> Result<String> {
+        if self.offline {
+            return Err(Offline("submit".to_string()).into());
+        }
+
+        let url = self.url(path);
+        let (boundary, body) = multipart(fields, file);
+        let response = self.send(Retry::Unsent, || {
+            self.client
+                .post(&url)
+                .header(
+                    CONTENT_TYPE,
+                    format!("multipart/form-data; boundary={}", boundary),
+                )
+                .body(body.clone())
+        })?;
+
+        match response.error_for_status() {
+            Ok(response) => Ok(response.text()?),
+            Err(err) => Err(FetchError(url, err.to_string()).into()),
+        }
+    }
+
+    /// Sends a request, retrying with exponential backoff when Kattis rate
+    /// limits us or fails, as far as `retry` allows. The request is rebuilt
+    /// for every attempt.
+    fn send<F: Fn() -> RequestBuilder>(&self, retry: Retry, request: F) -> Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            let result = self.with_cookies(request()).send();
+            if let Ok(response) = &result {
+                self.store_cookies(response.headers())?;
+            }
+
+            let again = match (&result, retry) {
+                (Ok(response), Retry::Idempotent) => {
+                    let status = response.status();
+                    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+                }
+                (Ok(response), Retry::Unsent) => response.status() == StatusCode::TOO_MANY_REQUESTS,
+                (Err(err), Retry::Idempotent) => err.is_timeout() || err.is_connect(),
+                (Err(err), Retry::Unsent) => err.is_connect(),
+            };
+
+            if !again || attempt >= self.retries {
+                return Ok(result?);
+            }
+
+            let delay = result
+                .ok()
+                .and_then(|r| header(r.headers(), RETRY_AFTER))
+                .and_then(|r| r.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or_else(|| {
+                    Duration::from_millis(500u64.saturating_mul(2u64.saturating_pow(attempt)))
+                });
+            sleep(delay.min(MAX_RETRY_DELAY));
+            attempt += 1;
+        }
+    }
+
+    fn with_cookies(&self, request: RequestBuilder) -> RequestBuilder {
+        let cookies = self.cookies.borrow();
+        if cookies.is_empty() {
+            return request;
+        }
+
+        let cookie = cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        request.header(COOKIE, cookie)
+    }
+
+    fn store_cookies(&self, headers: &HeaderMap) -> Result<()> {
+        let mut changed = false;
+        let mut cookies = self.cookies.borrow_mut();
+
+        for cookie in headers.get_all(SET_COOKIE) {
+            let cookie = match cookie.to_str() {
+                Ok(cookie) => cookie,
+                Err(_) => continue,
+            };
+            let pair = cookie.split(';').next().unwrap_or("");
+            if let Some((name, value)) = pair.split_once('=') {
+                cookies.insert(name.trim().to_string(), value.trim().to_string());
+                changed = true;
+            }
+        }
+
+        if changed {
+            if let Some(dir) = self.cookie_file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&self.cookie_file, serde_json::to_string_pretty(&*cookies)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Encodes fields and a single `(field, file name, contents)` file as a
+/// `multipart/form-data` body, returning the boundary and the body.
+fn multipart(fields: &[(&str, &str)], file: (&str, &str, &[u8])) -> (String, Vec<u8>) {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let boundary = format!("kah-{:x}", nanos);
+
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend(format!("--{}\r\n", boundary).bytes());
+        body.extend(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).bytes());
+        body.extend(format!("{}\r\n", value).bytes());
+    }
+
+    let (name, file_name, contents) = file;
+    body.extend(format!("--{}\r\n", boundary).bytes());
+    body.extend(
+        format!(
+            "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+            name, file_name
+        )
+        .bytes(),
+    );
+    body.extend(b"Content-Type: application/octet-stream\r\n\r\n");
+    body.extend(contents);
+    body.extend(format!("\r\n--{}--\r\n", boundary).bytes());
+
+    (boundary, body)
+}
+
+fn header(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|h| h.to_str().ok())
+        .map(|h| h.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+    };
+
+    /// Serves the given responses in order on a local port, sending every
+    /// request it receives back over the channel.
+    fn mock_server(responses: Vec<&'static str>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(&String::from_utf8_lossy(&body));
+                sender.send(request).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, receiver)
+    }
+
+    fn client(url: &str, dir: &Path) -> KattisClient {
+        let kattis = Kattis {
+            username: "user".into(),
+            token: Secret::new("token".into()),
+            hostname: "https://open.kattis.com".into(),
+            submit: "https://open.kattis.com/submit".into(),
+        };
+        let config = ClientConfig {
+            timeout: 5,
+            retries: 2,
+            base_url: Some(url.into()),
+        };
+
+        KattisClient::new(&kattis, &config, dir, false).unwrap()
+    }
+
+    #[test]
+    fn retries_server_errors() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let body = client(&url, dir.path())
+            .get_text("/problems/hello")
+            .unwrap();
+
+        assert_eq!(body, "hello");
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("GET /problems/hello "));
+        assert!(request
+            .to_lowercase()
+            .contains(&format!("user-agent: {}", USER_AGENT)));
+        assert!(requests.recv().is_ok());
+    }
+
+    #[test]
+    fn never_resends_failed_submissions() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 13\r\nConnection: close\r\n\r\nSubmission ID",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let result = client(&url, dir.path()).post_file(
+            "/submit",
+            &[("problem", "hello")],
+            ("sub_file[]", "hello.py", b"print(1)"),
+        );
+
+        assert!(result.is_err());
+        assert!(requests.recv().unwrap().starts_with("POST /submit "));
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[test]
+    fn keeps_cookies_of_retried_responses() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nSet-Cookie: EduSiteCookie=abc; Path=/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+
+        client(&url, dir.path()).get("/problems").unwrap();
+
+        requests.recv().unwrap();
+        let retried = requests.recv().unwrap().to_lowercase();
+        assert!(retried.contains("cookie: edusitecookie=abc"));
+    }
+
+    #[test]
+    fn moves_kattis_urls_to_the_base_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = client("http://127.0.0.1:8765", dir.path());
+
+        assert_eq!(
+            client.url("https://open.kattis.com/submit"),
+            "http://127.0.0.1:8765/submit"
+        );
+        assert_eq!(
+            client.url("https://open.kattis.com/contests/abc/problems?page=1"),
+            "http://127.0.0.1:8765/contests/abc/problems?page=1"
+        );
+        assert_eq!(
+            client.url("/problems/hello"),
+            "http://127.0.0.1:8765/problems/hello"
+        );
+        assert_eq!(
+            client.url("https://example.com/image.png"),
+            "https://example.com/image.png"
+        );
+    }
+
+    #[test]
+    fn keeps_cookies_between_clients() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nSet-Cookie: EduSiteCookie=abc; Path=/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+
+        client(&url, dir.path()).login().unwrap();
+        client(&url, dir.path()).get("/problems").unwrap();
+
+        assert!(requests.recv().unwrap().starts_with("POST /login "));
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.contains("cookie: edusitecookie=abc"));
+    }
+
+    #[test]
+    fn downloads_kattisrc_with_password() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nSet-Cookie: EduSiteCookie=abc; Path=/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 29\r\nConnection: close\r\n\r\n[user]\nusername: me\ntoken: t\n",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let client = client(&url, dir.path());
+
+        client.login_with_password("me", "secret").unwrap();
+        let kattisrc = client.download_kattisrc().unwrap();
+
+        assert!(kattisrc.starts_with("[user]"));
+        let login = requests.recv().unwrap();
+        assert!(login.starts_with("POST /login "));
+        assert!(login.ends_with("user=me&password=secret&script=true"));
+        let download = requests.recv().unwrap().to_lowercase();
+        assert!(download.starts_with("get /download/kattisrc "));
+        assert!(download.contains("cookie: edusitecookie=abc"));
+    }
+
+    #[test]
+    fn rejects_login_pages_as_kattisrc() {
+        let (url, _requests) = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 19\r\nConnection: close\r\n\r\n<html>Log in</html>",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(client(&url, dir.path()).download_kattisrc().is_err());
+    }
+
+    #[test]
+    fn posts_files_as_multipart() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 13\r\nConnection: close\r\n\r\nSubmission ID",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let body = client(&url, dir.path())
+            .post_file(
+                "/submit",
+                &[("problem", "hello")],
+                ("sub_file[]", "hello.py", b"print(1)"),
+            )
+            .unwrap();
+
+        assert_eq!(body, "Submission ID");
+        let request = requests.recv().unwrap();
+        assert!(request
+            .to_lowercase()
+            .contains("content-type: multipart/form-data; boundary=kah-"));
+        assert!(request.contains("name=\"problem\"\r\n\r\nhello\r\n"));
+        assert!(request.contains("filename=\"hello.py\"\r\n"));
+        assert!(request.contains("print(1)\r\n--kah-"));
+    }
+
+    #[test]
+    fn revalidates_cached_responses() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 5\r\nConnection: close\r\n\r\nfirst",
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let client = client(&url, dir.path());
+
+        assert_eq!(client.get_text("/problems/hello").unwrap(), "first");
+        assert_eq!(client.get_text("/problems/hello").unwrap(), "first");
+
+        requests.recv().unwrap();
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.contains("if-none-match: \"v1\""));
+    }
+}
@@ -0,0 +1,452 @@
+use crate::{
+    error::KahError::ScrapeError,
+    kah::Kah,
+    languages::Languages,
+    layout::{Layout, ProblemPaths},
+    problem::{collapse_whitespace, find_labelled, ProblemMetadata},
+    utils::write_atomically,
+    ForceProblemCreation,
+};
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use reqwest::Url;
+use select::{
+    document::Document,
+    node::Node,
+    predicate::{Attr, Name, Predicate},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// A problem in a contest, e.g. problem `A` being `hello`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct ContestProblem {
+    pub(crate) letter: String,
+    pub(crate) id: String,
+    pub(crate) name: String,
+}
+
+/// A Kattis contest or course session and the problems in it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Contest {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) problems: Vec<ContestProblem>,
+    #[serde(default)]
+    pub(crate) start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) end: Option<DateTime<Utc>>,
+}
+
+impl Contest {
+    /// Fetches a contest from either its ID or the URL of its problem list.
+    pub(crate) fn get(kah: &Kah, contest: &str) -> Result<Contest> {
+        let url = contest_url(&kah.get_kattis_url(), contest)?;
+        let id = contest_id(&url)
+            .ok_or_else(|| ScrapeError(contest.to_string(), "Not a contest URL".to_string()))?;
+        let body = kah.client.get_text(url.as_str())?;
+
+        Contest::parse(&id, url.as_str(), &body)
+    }
+
+    /// Scrapes the problems of a contest from its problem list. Letters are
+    /// taken from the first cell of each row, or given in order when the
+    /// list has none.
+    pub(crate) fn parse(id: &str, url: &str, body: &str) -> Result<Contest> {
+        let document = Document::from(body);
+
+        let name = document
+            .find(Name("h1").or(Name("h2")))
+            .map(|n| collapse_whitespace(&n.text()))
+            .find(|n| !n.is_empty())
+            .unwrap_or_else(|| id.to_string());
+
+        let mut problems: Vec<ContestProblem> = Vec::new();
+        for link in document.find(Name("a").and(Attr("href", ()))) {
+            let problem = match link.attr("href").and_then(problem_id) {
+                Some(problem) => problem,
+                None => continue,
+            };
+            if problems.iter().any(|p| p.id == problem) {
+                continue;
+            }
+
+            let letter =
+                row_letter(&link).unwrap_or_else(|| letter_for(problems.len()).to_string());
+            problems.push(ContestProblem {
+                letter,
+                id: problem,
+                name: collapse_whitespace(&link.text()),
+            });
+        }
+
+        if problems.is_empty() {
+            return Err(ScrapeError(id.to_string(), "Could not find any problems".into()).into());
+        }
+
+        let start =
+            find_labelled(&document, &["Start time", "Starts"]).and_then(|t| parse_time(&t));
+        let end = find_labelled(&document, &["End time", "Ends"])
+            .and_then(|t| parse_time(&t))
+            .or_else(|| {
+                find_labelled(&document, &["Time remaining", "Remaining"])
+                    .and_then(|t| parse_remaining(&t))
+                    .map(|remaining| Utc::now() + remaining)
+            });
+
+        Ok(Contest {
+            id: id.to_string(),
+            name,
+            url: url.to_string(),
+            problems,
+            start,
+            end,
+        })
+    }
+
+    /// URL of the contest itself, without the trailing `/problems`.
+    pub(crate) fn base_url(&self) -> &str {
+        self.url.trim_end_matches('/').trim_end_matches("/problems")
+    }
+
+    /// Where submissions to problems in the contest are sent.
+    pub(crate) fn submit_url(&self) -> String {
+        format!("{}/submit", self.base_url())
+    }
+
+    /// A contest without known start and end times is always running.
+    pub(crate) fn is_running(&self, now: DateTime<Utc>) -> bool {
+        self.start.is_none_or(|start| start <= now) && self.end.is_none_or(|end| now < end)
+    }
+
+    pub(crate) fn problem(&self, id: &str) -> Option<&ContestProblem> {
+        self.problems.iter().find(|p| p.id == id)
+    }
+
+    /// Directory the contest is created in, relative to the code directory.
+    pub(crate) fn dir(&self) -> PathBuf {
+        Path::new("contests").join(&self.id)
+    }
+
+    /// Paths of a problem in the contest, relative to the code directory. The
+    /// layout is used inside the contest directory with the letter of the
+    /// problem in front, e.g. `contests/abc123/python/A_HelloWorld.py`, so
+    /// the name is still a valid class name for Java.
+    pub(crate) fn problem_paths(
+        &self,
+        letter: &str,
+        problem: &ProblemMetadata,
+        language: &Languages,
+        layout: &Layout,
+    ) -> ProblemPaths {
+        layout
+            .paths(problem, language, &format!("{}_", letter))
+            .within(&self.dir())
+    }
+
+    /// Fetches and creates every problem in the contest, carrying on when a
+    /// problem fails. Problems that are already tracked are skipped unless
+    /// forced. Returns how many problems were created.
+    pub(crate) fn create(
+        &self,
+        kah: &mut Kah,
+        language: &Languages,
+        force: ForceProblemCreation,
+    ) -> Result<usize> {
+        let tracked: HashSet<String> = kah.problems()?.into_iter().map(|p| p.metadata.id).collect();
+        let layout = kah.layout()?;
+        let mut created = 0;
+
+        for (i, problem) in self.problems.iter().enumerate() {
+            println!(
+                "[{}/{}] {}: {}",
+                i + 1,
+                self.problems.len(),
+                problem.letter,
+                problem.id
+            );
+            if tracked.contains(&problem.id) && !force.recreate_metadata() {
+                println!("{} is already tracked, skipping it", problem.id);
+                continue;
+            }
+
+            let result = ProblemMetadata::new(kah, &problem.id).and_then(|metadata| {
+                let paths = self.problem_paths(&problem.letter, &metadata, language, &layout);
+                kah.create_problem_at(&metadata, language.clone(), &paths, force)
+            });
+            match result {
+                Ok(()) => created += 1,
+                Err(err) => eprintln!("Could not create {}: {}", problem.id, err),
+            }
+        }
+
+        Ok(created)
+    }
+}
+
+impl Kah {
+    /// The contest fetched last is kept next to the datafile.
+    fn contest_file(&self) -> PathBuf {
+        self.datafile_dir().join("contest.json")
+    }
+
+    pub(crate) fn save_contest(&self, contest: &Contest) -> Result<()> {
+        let json = serde_json::to_string_pretty(contest)?;
+        write_atomically(&self.contest_file(), json.as_bytes())
+    }
+
+    /// Returns the contest fetched last, if any.
+    pub(crate) fn contest(&self) -> Result<Option<Contest>> {
+        let path = self.contest_file();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_str(&read_to_string(path)?)?))
+    }
+}
+
+/// Turns a contest ID into the URL of its problem list, URLs are used as they
+/// are except that the problem list is used for contest front pages.
+fn contest_url(base: &str, contest: &str) -> Result<Url> {
+    if !(contest.starts_with("http://") || contest.starts_with("https://")) {
+        return Ok(Url::parse(&format!(
+            "{}/contests/{}/problems",
+            base, contest
+        ))?);
+    }
+
+    let mut url = Url::parse(contest)?;
+    let segments: Vec<String> = url
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).map(String::from).collect())
+        .unwrap_or_default();
+    if let [.., contests, _] = &segments[..] {
+        if contests == "contests" {
+            url.set_path(&format!("{}/problems", segments.join("/")));
+        }
+    }
+
+    Ok(url)
+}
+
+/// The contest or session ID is the last part of the URL before `problems`.
+fn contest_id(url: &Url) -> Option<String> {
+    url.path_segments()?
+        .rfind(|s| !s.is_empty() && *s != "problems")
+        .map(String::from)
+}
+
+/// Finds the problem ID in links like `/problems/hello` and
+/// `/contests/abc123/problems/hello`, but not in links to pages about a
+/// problem like `/problems/hello/statistics`.
+pub(crate) fn problem_id(href: &str) -> Option<String> {
+    let path = href.split(['?', '#']).next()?;
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match segments[..] {
+        [.., "problems", id] => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// The letter of a problem in a table is in the first cell of its row.
+fn row_letter(link: &Node) -> Option<String> {
+    let row = std::iter::successors(link.parent(), |n| n.parent()).find(|n| n.is(Name("tr")))?;
+    let cell = row.find(Name("th").or(Name("td"))).next()?;
+    let letter = collapse_whitespace(&cell.text());
+
+    if !letter.is_empty() && letter.len() <= 2 && letter.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        Some(letter)
+    } else {
+        None
+    }
+}
+
+fn letter_for(index: usize) -> char {
+    (b'A' + (index % 26) as u8) as char
+}
+
+/// Parses times like `2021-03-06 11:00 CET`, `2021-03-06 11:00:00 +01:00` or
+/// RFC 3339 timestamps. Times without a zone are taken to be in UTC.
+fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&Utc));
+    }
+
+    let mut parts = text.split_whitespace();
+    let date = parts.next()?;
+    let time = parts.next()?;
+    let time = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M"))
+        .ok()?;
+    let offset = match parts.next() {
+        Some(zone) => zone_offset(zone)?,
+        None => 0,
+    };
+
+    let offset = FixedOffset::east_opt(offset)?;
+    Some(
+        offset
+            .from_local_datetime(&time)
+            .single()?
+            .with_timezone(&Utc),
+    )
+}
+
+/// Offset in seconds of a zone like `+01:00`, `+0100` or a common
+/// abbreviation.
+fn zone_offset(zone: &str) -> Option<i32> {
+    let hours = match zone.to_uppercase().as_str() {
+        "UTC" | "GMT" | "Z" | "WET" => 0,
+        "CET" | "WEST" | "BST" => 1,
+        "CEST" | "EET" => 2,
+        "EEST" | "MSK" => 3,
+        "EDT" => -4,
+        "EST" | "CDT" => -5,
+        "CST" | "MDT" => -6,
+        "MST" | "PDT" => -7,
+        "PST" => -8,
+        _ => {
+            let sign = match zone.chars().next()? {
+                '+' => 1,
+                '-' => -1,
+                _ => return None,
+            };
+            let digits: String = zone[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+            if digits.len() != 4 {
+                return None;
+            }
+            let hours: i32 = digits[..2].parse().ok()?;
+            let minutes: i32 = digits[2..].parse().ok()?;
+            return Some(sign * (hours * 3600 + minutes * 60));
+        }
+    };
+
+    Some(hours * 3600)
+}
+
+/// Parses a countdown like `1:02:03`, or `2 days 1:02:03`.
+fn parse_remaining(text: &str) -> Option<Duration> {
+    let mut days = 0;
+    let mut clock = None;
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    for (i, word) in words.iter().enumerate() {
+        if word.contains(':') {
+            clock = Some(*word);
+        } else if words
+            .get(i + 1)
+            .is_some_and(|next| next.to_lowercase().starts_with("day"))
+        {
+            days = word.parse().ok()?;
+        }
+    }
+
+    let parts: Vec<i64> = clock?
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let seconds = match parts[..] {
+        [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+        [minutes, seconds] => minutes * 60 + seconds,
+        _ => return None,
+    };
+
+    Some(Duration::days(days) + Duration::seconds(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTEST_PROBLEMS: &str = include_str!("../tests/fixtures/contest_problems.html");
+    const SESSION_PROBLEMS: &str = include_str!("../tests/fixtures/session_problems.html");
+
+    #[test]
+    fn parses_contest_problems() {
+        let contest = Contest::parse("abc123", "", CONTEST_PROBLEMS).unwrap();
+        let problems: Vec<_> = contest
+            .problems
+            .iter()
+            .map(|p| (p.letter.as_str(), p.id.as_str()))
+            .collect();
+
+        assert_eq!(contest.name, "Spring Practice 2021");
+        assert_eq!(
+            problems,
+            [("A", "hello"), ("B", "twostones"), ("C", "carrots")]
+        );
+        assert_eq!(contest.problems[1].name, "Take Two Stones");
+        assert_eq!(
+            contest.start.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2021-03-06T10:00:00+00:00")
+        );
+        assert_eq!(
+            contest.end.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2021-03-06T15:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn letters_problems_in_order() {
+        let contest = Contest::parse("3", "", SESSION_PROBLEMS).unwrap();
+        let problems: Vec<_> = contest
+            .problems
+            .iter()
+            .map(|p| (p.letter.as_str(), p.id.as_str()))
+            .collect();
+
+        assert_eq!(contest.name, "Session 3");
+        assert_eq!(problems, [("A", "aplusb"), ("B", "quickscope")]);
+    }
+
+    #[test]
+    fn parses_times() {
+        let time = |t| parse_time(t).map(|t| t.to_rfc3339());
+
+        assert_eq!(
+            time("2021-03-06 11:00:30 +01:00").as_deref(),
+            Some("2021-03-06T10:00:30+00:00")
+        );
+        assert_eq!(
+            time("2021-03-06T11:00:00Z").as_deref(),
+            Some("2021-03-06T11:00:00+00:00")
+        );
+        assert_eq!(time("2021-03-06 11:00 XYZ"), None);
+        assert_eq!(parse_remaining("1:02:03"), Some(Duration::seconds(3723)));
+        assert_eq!(
+            parse_remaining("2 days 0:00:10"),
+            Some(Duration::days(2) + Duration::seconds(10))
+        );
+    }
+
+    #[test]
+    fn resolves_contest_urls() {
+        let base = "https://open.kattis.com";
+        let url = |c| contest_url(base, c).unwrap().to_string();
+
+        assert_eq!(
+            url("abc123"),
+            "https://open.kattis.com/contests/abc123/problems"
+        );
+        assert_eq!(
+            url("https://open.kattis.com/contests/abc123"),
+            "https://open.kattis.com/contests/abc123/problems"
+        );
+        assert_eq!(
+            url("https://kth.kattis.com/courses/DD2458/popup21/sessions/3"),
+            "https://kth.kattis.com/courses/DD2458/popup21/sessions/3"
+        );
+        assert_eq!(
+            contest_id(&Url::parse(&url("abc123")).unwrap()).as_deref(),
+            Some("abc123")
+        );
+    }
+}
@@ -3,6 +3,8 @@
 
 using namespace std;
 
+// kah: snippets
+
 int main() {
   std::ios::sync_with_stdio(false);
   std::cin.tie(nullptr);
@@ -1,3 +1,5 @@
+-- kah: snippets
+
 readInput = (map read) . words
 writeOutput = unlines . (map show)
 
@@ -1,4 +1,6 @@
-public class Problem {
+public class {{class_name}} {
+    // kah: snippets
+
     public static void main(String[] args) {
         System.out.println("Hello, World");
     }
@@ -1,3 +1,5 @@
+// kah: snippets
+
 fun main() {
     println("Hello, World!")
 }
\ No newline at end of file
@@ -1,2 +1,4 @@
+# kah: snippets
+
 num = int(input())
 print(f"{num * 2}")
\ No newline at end of file
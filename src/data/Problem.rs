@@ -1,5 +1,7 @@
 use std::io::{self, BufRead};
 
+// kah: snippets
+
 fn main() {
     let stdin = io::stdin();
     for line in stdin.lock().lines().map(|l| l.unwrap()) {
@@ -1,18 +1,65 @@
-use crate::{kah::Kah, languages::Languages, problem::ProblemMetadata, ForceProblemCreation};
+use crate::{
+    error::KahError::{NoBackups, NoSuchBackup},
+    kah::Kah,
+    language::problem_path,
+    languages::Languages,
+    layout::ProblemPaths,
+    problem::ProblemMetadata,
+    test::TestResult,
+    utils::{fuzzy_score, write_atomically, FuzzyScore},
+    ForceProblemCreation,
+};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
+    ffi::OsStr,
     fmt::Formatter,
+    fs,
     fs::{read_to_string, File, OpenOptions},
-    io::Write,
+    io,
+    io::ErrorKind,
+    path::{Path, PathBuf},
     process::exit,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct Solution {
     pub(crate) language: Languages,
     pub(crate) solved: bool,
+    #[serde(default)]
+    pub(crate) touched: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) solved_at: Option<DateTime<Utc>>,
+    /// Path of the solution relative to the code directory
+    #[serde(default)]
+    pub(crate) path: Option<PathBuf>,
+    /// Result of the last local test run
+    #[serde(default)]
+    pub(crate) tested: Option<TestRun>,
+    /// Directory with extra test cases, relative to the code directory
+    #[serde(default)]
+    pub(crate) tests: Option<PathBuf>,
+    /// Path of the statement relative to the code directory
+    #[serde(default)]
+    pub(crate) statement: Option<PathBuf>,
+}
+
+/// How many samples passed the last time a solution was tested.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct TestRun {
+    pub(crate) passed: usize,
+    pub(crate) total: usize,
+    pub(crate) at: DateTime<Utc>,
+}
+
+impl TestRun {
+    pub(crate) fn ok(&self) -> bool {
+        self.passed == self.total
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,6 +89,23 @@ impl PartialEq for Problem {
 }
 
 impl Problem {
+    /// Path of the solution relative to the code directory.
+    pub(crate) fn solution_path(&self) -> PathBuf {
+        match &self.solution.path {
+            Some(path) => path.clone(),
+            None => PathBuf::from(problem_path(&self.solution.language, &self.metadata)),
+        }
+    }
+
+    /// Path of the statement relative to the code directory, next to the
+    /// solution unless the layout put it elsewhere.
+    pub(crate) fn statement_path(&self) -> PathBuf {
+        match &self.solution.statement {
+            Some(path) => path.clone(),
+            None => self.solution_path().with_extension("md"),
+        }
+    }
+
     pub(crate) fn check_output(&self, expected: &str, output: String) -> bool {
         let expected: String = expected.trim_end().lines().map(|s| s.trim_end()).collect();
         let actual: String = output.trim_end().lines().map(|s| s.trim_end()).collect();
@@ -56,10 +120,7 @@ impl Kah {
             eprintln!("Data file already exists.");
             exit(0);
         } else {
-            let mut file = File::create(&self.config.data)?;
-            let map: HashMap<String, Problem> = HashMap::new();
-            let json = serde_json::to_string_pretty(&map)?;
-            file.write_all(&json.into_bytes())?;
+            self.write_datafile(&HashMap::new(), true)?;
         }
 
         println!("Data file successfully created");
@@ -71,8 +132,10 @@ impl Kah {
         &mut self,
         problem: &ProblemMetadata,
         language: &Languages,
+        paths: &ProblemPaths,
         force: ForceProblemCreation,
     ) -> Result<()> {
+        let _lock = self.lock_datafile()?;
         let mut problems = self.open_datafile()?;
         if problems.contains_key(&problem.id) && !force.recreate_metadata() {
             eprintln!("Datafile already contains {}, aborting", problem.name);
@@ -86,35 +149,178 @@ impl Kah {
                 solution: Solution {
                     language: language.clone(),
                     solved: false,
+                    touched: Some(Utc::now()),
+                    solved_at: None,
+                    path: Some(paths.solution.clone()),
+                    tested: None,
+                    tests: paths.tests.clone(),
+                    statement: Some(paths.statement.clone()),
                 },
             },
         );
 
-        self.write_datafile(&problems)?;
+        self.write_datafile(&problems, true)?;
+
+        Ok(())
+    }
+
+    /// Returns every tracked problem.
+    pub(crate) fn problems(&self) -> Result<Vec<Problem>> {
+        Ok(self.open_datafile()?.into_values().collect())
+    }
+
+    /// Marks a problem as recently worked on. Like test results this is not
+    /// worth a backup, which would push out the ones of real changes.
+    pub(crate) fn touch_problem(&self, id: &str) -> Result<()> {
+        self.update_problem(id, false, |problem| {
+            problem.solution.touched = Some(Utc::now())
+        })
+    }
+
+    /// Marks a problem as solved or unsolved, recording when it was solved.
+    pub(crate) fn solve_problem(&self, id: &str, solved: bool) -> Result<()> {
+        self.update_problem(id, true, |problem| {
+            let solution = &mut problem.solution;
+            if solved && !solution.solved {
+                solution.solved_at = Some(Utc::now());
+            } else if !solved {
+                solution.solved_at = None;
+            }
+            solution.solved = solved;
+            solution.touched = Some(Utc::now());
+        })
+    }
+
+    /// Records the result of testing a problem locally.
+    pub(crate) fn record_test(&self, id: &str, result: &TestResult) -> Result<()> {
+        let run = TestRun {
+            passed: result.results.iter().filter(|r| **r).count(),
+            total: result.results.len(),
+            at: Utc::now(),
+        };
+        self.update_problem(id, false, |problem| problem.solution.tested = Some(run))
+    }
+
+    /// Updates a problem if it is tracked, `backup` is whether the old
+    /// datafile is backed up first.
+    fn update_problem<F: FnOnce(&mut Problem)>(
+        &self,
+        id: &str,
+        backup: bool,
+        update: F,
+    ) -> Result<()> {
+        let _lock = self.lock_datafile()?;
+        let mut problems = self.open_datafile()?;
+        if let Some(problem) = problems.get_mut(id) {
+            update(problem);
+            self.write_datafile(&problems, backup)?;
+        }
 
         Ok(())
     }
 
-    pub(crate) fn get_problem(&self, id: &str) -> Option<Problem> {
-        let problems = self.open_datafile().ok()?;
+    /// Stores the statement of a problem fetched after it was created.
+    pub(crate) fn set_statement(&self, id: &str, statement: Option<String>) -> Result<()> {
+        self.update_problem(id, true, |problem| problem.metadata.statement = statement)
+    }
 
-        problems
+    /// The id of a problem given on the command line without looking it up
+    /// fuzzily: a tracked id, or something that looks like a Kattis id even
+    /// if it is not tracked. Anything else, e.g. a name, gives `None`.
+    pub(crate) fn problem_id(&self, query: &str) -> Result<Option<String>> {
+        let is_id = !query.is_empty()
+            && query
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+
+        if is_id || self.open_datafile()?.contains_key(query) {
+            Ok(Some(query.to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Finds the problems matching `query` by their id or name, ranked by how
+    /// well they match, best first. An exact id match is the only result.
+    pub(crate) fn find_problems(&self, query: &str) -> Result<Vec<(FuzzyScore, Problem)>> {
+        let problems = self.open_datafile()?;
+
+        if let Some(problem) = problems.get(query) {
+            return Ok(vec![(FuzzyScore::exact(), problem.clone())]);
+        }
+
+        let mut matches: Vec<_> = problems
             .values()
-            .cloned()
-            .find(|p| p.metadata.id.contains(id) || p.metadata.name.contains(id))
+            .filter_map(|p| {
+                let score =
+                    fuzzy_score(query, &p.metadata.id).max(fuzzy_score(query, &p.metadata.name))?;
+                Some((score, p.clone()))
+            })
+            .collect();
+        matches.sort_unstable_by(|(s, p), (o, q)| {
+            o.cmp(s).then_with(|| p.metadata.id.cmp(&q.metadata.id))
+        });
+
+        Ok(matches)
     }
 
+    /// Rewrites the datafile in the current format, which does nothing when
+    /// it is up to date.
     pub(crate) fn update(&mut self) -> Result<()> {
+        let _lock = self.lock_datafile()?;
+        let problems = self.open_datafile()?;
+        self.write_datafile(&problems, true)?;
+
         Ok(())
     }
 
+    /// Lists the available backups of the datafile, newest first.
+    pub(crate) fn datafile_backups(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.backup_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| backup_timestamp(path).is_some())
+            .collect();
+        backups.sort_unstable_by_key(|path| std::cmp::Reverse(backup_timestamp(path)));
+
+        Ok(backups)
+    }
+
+    /// Restores the datafile from a backup, either by its file name or the
+    /// most recent one. The current datafile is itself backed up first, so a
+    /// restore can be undone.
+    pub(crate) fn restore_datafile(&self, backup: Option<&str>) -> Result<PathBuf> {
+        let _lock = self.lock_datafile()?;
+        let backups = self.datafile_backups()?;
+
+        let path = match backup {
+            Some(name) => backups
+                .into_iter()
+                .find(|p| p.file_name() == Some(OsStr::new(name)))
+                .ok_or_else(|| NoSuchBackup(name.to_string()))?,
+            None => backups.into_iter().next().ok_or(NoBackups)?,
+        };
+
+        let file = read_to_string(&path)?;
+        let problems: HashMap<String, Problem> = serde_json::from_str(&file)?;
+        self.write_datafile(&problems, true)?;
+
+        Ok(path)
+    }
+
     pub(crate) fn datafile_exists(&self) -> bool {
         self.config.data.exists()
     }
 
+    /// Reads the datafile, which is empty until something is written to it.
     fn open_datafile(&self) -> Result<HashMap<String, Problem>> {
         if !self.datafile_exists() {
-            self.write_datafile(&HashMap::new())?;
+            return Ok(HashMap::new());
         }
 
         let file = read_to_string(&self.config.data)?;
@@ -123,16 +329,212 @@ impl Kah {
         Ok(result)
     }
 
-    fn write_datafile(&self, datafile: &HashMap<String, Problem>) -> Result<()> {
+    /// Writes the datafile atomically, backing up the previous version if
+    /// `backup` is set. Nothing is written when nothing changed.
+    fn write_datafile(&self, datafile: &HashMap<String, Problem>, backup: bool) -> Result<()> {
         let json = serde_json::to_string_pretty(&datafile)?;
-        let mut file = OpenOptions::new()
+        if read_to_string(&self.config.data).is_ok_and(|current| current == json) {
+            return Ok(());
+        }
+
+        if backup {
+            self.backup_datafile()?;
+        }
+        write_atomically(&self.config.data, json.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Takes an exclusive advisory lock for a read-modify-write cycle of the
+    /// datafile, the lock is released when the returned file is dropped.
+    fn lock_datafile(&self) -> Result<File> {
+        fs::create_dir_all(self.datafile_dir())?;
+        let file = OpenOptions::new()
             .create(true)
+            .truncate(false)
             .write(true)
-            .append(false)
-            .open(&self.config.data)?;
+            .open(self.datafile_dir().join("data.lock"))?;
+        file.lock_exclusive()?;
 
-        file.write_all(&json.into_bytes())?;
+        Ok(file)
+    }
+
+    fn backup_datafile(&self) -> Result<()> {
+        if !self.datafile_exists() || self.config.backups == 0 {
+            return Ok(());
+        }
+
+        let dir = self.backup_dir();
+        fs::create_dir_all(&dir)?;
+
+        // Backups taken in the same millisecond go to the next free one, so
+        // none are overwritten and they still sort by age
+        let mut timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let mut backup = loop {
+            let path = dir.join(format!("data.{}.json", timestamp));
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(file) => break file,
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => timestamp += 1,
+                Err(err) => return Err(err.into()),
+            }
+        };
+        io::copy(&mut File::open(&self.config.data)?, &mut backup)?;
+
+        for old in self.datafile_backups()?.iter().skip(self.config.backups) {
+            fs::remove_file(old)?;
+        }
 
         Ok(())
     }
+
+    pub(crate) fn datafile_dir(&self) -> PathBuf {
+        match self.config.data.parent() {
+            Some(dir) => dir.to_owned(),
+            None => self.config.dir.clone(),
+        }
+    }
+
+    fn backup_dir(&self) -> PathBuf {
+        self.datafile_dir().join("backups")
+    }
+}
+
+fn backup_timestamp(path: &Path) -> Option<u128> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("data.")?
+        .strip_suffix(".json")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::KattisClient, kah::Kattis};
+
+    fn kah(dir: &Path, backups: usize) -> Kah {
+        let config = serde_json::json!({
+            "code": dir,
+            "dir": dir,
+            "file": dir.join("config.json"),
+            "data": dir.join("data.json"),
+            "backups": backups,
+        });
+
+        Kah {
+            config: serde_json::from_value(config).unwrap(),
+            kattis: Kattis {
+                username: "user".to_string(),
+                token: Default::default(),
+                hostname: "https://open.kattis.com".to_string(),
+                submit: "https://open.kattis.com/submit".to_string(),
+            },
+            workspace: None,
+            client: KattisClient::default(),
+        }
+    }
+
+    fn add_hello(kah: &mut Kah) {
+        let problem = ProblemMetadata {
+            name: "Hello World!".to_string(),
+            id: "hello".to_string(),
+            cpu_time_limit: "1 second".to_string(),
+            memory_limit: "1024 MB".to_string(),
+            difficulty: 1.2,
+            samples: Vec::new(),
+            statement: None,
+        };
+        let paths = ProblemPaths {
+            solution: PathBuf::from("python/HelloWorld.py"),
+            statement: PathBuf::from("python/HelloWorld.md"),
+            notes: None,
+            tests: None,
+        };
+        kah.add_problem(
+            &problem,
+            &Languages::Python,
+            &paths,
+            ForceProblemCreation::Nothing,
+        )
+        .unwrap();
+    }
+
+    fn solved_in(backup: &Path) -> bool {
+        let problems: HashMap<String, Problem> =
+            serde_json::from_str(&read_to_string(backup).unwrap()).unwrap();
+        problems["hello"].solution.solved
+    }
+
+    #[test]
+    fn rotates_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut kah = kah(dir.path(), 2);
+        add_hello(&mut kah);
+        assert!(kah.datafile_backups().unwrap().is_empty());
+
+        kah.solve_problem("hello", true).unwrap();
+        kah.solve_problem("hello", false).unwrap();
+        kah.solve_problem("hello", true).unwrap();
+
+        // Newest first, the oldest of the three was removed
+        let backups = kah.datafile_backups().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(!solved_in(&backups[0]));
+        assert!(solved_in(&backups[1]));
+    }
+
+    #[test]
+    fn only_backs_up_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut kah = kah(dir.path(), 5);
+        add_hello(&mut kah);
+
+        kah.touch_problem("hello").unwrap();
+        kah.record_test(
+            "hello",
+            &TestResult {
+                timings: Vec::new(),
+                results: vec![true],
+            },
+        )
+        .unwrap();
+        kah.update().unwrap();
+        assert!(kah.datafile_backups().unwrap().is_empty());
+
+        let problems = kah.problems().unwrap();
+        assert!(problems[0].solution.tested.as_ref().unwrap().ok());
+    }
+
+    #[test]
+    fn finds_problem_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut kah = kah(dir.path(), 5);
+        add_hello(&mut kah);
+
+        assert_eq!(kah.problem_id("hello").unwrap().as_deref(), Some("hello"));
+        // Untracked ids are not matched against tracked problems
+        assert_eq!(kah.problem_id("hel").unwrap().as_deref(), Some("hel"));
+        assert_eq!(kah.problem_id("Hello World").unwrap(), None);
+        assert_eq!(kah.find_problems("Hello World").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn restores_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut kah = kah(dir.path(), 5);
+        add_hello(&mut kah);
+        kah.solve_problem("hello", true).unwrap();
+
+        let backups = kah.datafile_backups().unwrap();
+        let name = backups[0].file_name().unwrap().to_str().unwrap();
+        assert_eq!(kah.restore_datafile(Some(name)).unwrap(), backups[0]);
+        assert!(!kah.problems().unwrap()[0].solution.solved);
+
+        // The restore backed up the datafile where it was solved
+        kah.restore_datafile(None).unwrap();
+        assert!(kah.problems().unwrap()[0].solution.solved);
+
+        assert!(kah.restore_datafile(Some("data.1.json")).is_err());
+    }
 }
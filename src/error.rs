@@ -12,6 +12,66 @@ pub enum KahError {
     NoSuchProblem(String),
     #[error("No such flag: {0}")]
     ForceProblemCreationError(u64),
-    #[error("Could not parse kattisrc")]
-    KattisrcParseError,
+    #[error("Could not read kattisrc {0}: {1}")]
+    KattisrcParseError(String, String),
+    #[error("The kattisrc has no `{1}` in its [{0}] section")]
+    KattisrcMissingKey(String, String),
+    #[error("Could not find a kattisrc, download it from Kattis and give its path")]
+    NoKattisrc,
+    #[error("kah is not set up yet, run `kah init` first")]
+    NoConfig,
+    #[error("No such profile: {0}")]
+    NoSuchProfile(String),
+    #[error("Profile {0} already exists, use --force to replace it")]
+    ProfileExists(String),
+    #[error("There are several profiles, choose one with --profile or set a default")]
+    NoDefaultProfile,
+    #[error("No such backup of the datafile: {0}")]
+    NoSuchBackup(String),
+    #[error("There are no backups of the datafile")]
+    NoBackups,
+    #[error("Could not log in as {0}: {1}")]
+    LoginError(String, String),
+    #[error("{0} is not cached, run the command again without --offline")]
+    NotCached(String),
+    #[error("Cannot {0} while offline")]
+    Offline(String),
+    #[error("Could not submit {0}: {1}")]
+    SubmitError(String, String),
+    #[error("There is no contest, fetch one with `kah contest <id>`")]
+    NoContest,
+    #[error("Wrong passphrase for the stored credentials")]
+    WrongPassphrase,
+    #[error("Could not store secret with `{0}`: {1}")]
+    SecretCommandError(String, String),
+    #[error("There is no token stored for {0}, run `kah init` again")]
+    NoToken(String),
+    #[error("Could not find the statement of {0}")]
+    NoStatement(String),
+    #[error("There is no snippet {0} for {1}")]
+    NoSuchSnippet(String, String),
+    #[error("{0} has no `{1}` comment to insert snippets at")]
+    NoSnippetMarker(String, String),
+    #[error("Could not compile {0}:\n{1}")]
+    CompileError(String, String),
+    #[error("Testing {0} solutions locally is not supported yet")]
+    CannotTest(String),
+    #[error("{0} fails its tests, use --force to submit it anyway")]
+    TestsFailed(String),
+    #[error("{0} has no samples to test with, use --force to submit it anyway")]
+    NoSamples(String),
+    #[error("No layout named {0}, use flat, problem or a table of paths")]
+    UnknownLayout(String),
+    #[error("{0} is already the solution of {1}")]
+    SolutionPathTaken(String, String),
+    #[error("No editor set, set `editor` in the configuration or $EDITOR")]
+    NoEditor,
+    #[error("Could not run the editor `{0}`: {1}")]
+    EditorError(String, String),
+    #[error("{0} is imported in a cycle, which cannot be bundled")]
+    CircularImport(String),
+    #[error("No problem left to pick, try other filters")]
+    NoRandomProblem,
+    #[error("Cannot sort problems by {0}")]
+    SortParseError(String),
 }
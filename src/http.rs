@@ -0,0 +1,149 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// What we know about a cached response, stored next to its body.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    pub(crate) url: String,
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+/// A cache of responses from Kattis on disk, keyed by their URL.
+#[derive(Debug, Default)]
+pub(crate) struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub(crate) fn new(dir: &Path) -> Self {
+        HttpCache {
+            dir: dir.to_owned(),
+        }
+    }
+
+    pub(crate) fn get(&self, url: &str) -> Option<(CacheEntry, Vec<u8>)> {
+        let (entry, body) = self.paths(url);
+
+        let entry = read_to_string(entry)
+            .ok()
+            .and_then(|e| serde_json::from_str::<CacheEntry>(&e).ok())
+            // Guard against the unlikely case of a hash collision
+            .filter(|e| e.url == url)?;
+        let body = fs::read(body).ok()?;
+
+        Some((entry, body))
+    }
+
+    pub(crate) fn store(&self, entry: &CacheEntry, body: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let (entry_path, body_path) = self.paths(&entry.url);
+
+        fs::write(body_path, body)?;
+        fs::write(entry_path, serde_json::to_string_pretty(entry)?)?;
+
+        Ok(())
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = format!("{:016x}", fnv1a(url.as_bytes()));
+
+        (
+            self.dir.join(format!("{}.json", key)),
+            self.dir.join(format!("{}.body", key)),
+        )
+    }
+}
+
+/// A small, stable hash for naming cache files, unlike the standard library
+/// hasher it does not change between Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::{ClientConfig, KattisClient},
+        error::KahError,
+        kah::Kattis,
+        secret::Secret,
+    };
+    use std::net::TcpListener;
+
+    /// A client that is offline, with a base URL nothing answers on unless
+    /// the listener accepts.
+    fn offline_client(dir: &Path, listener: &TcpListener) -> KattisClient {
+        let kattis = Kattis {
+            username: "user".into(),
+            token: Secret::new("token".into()),
+            hostname: "https://open.kattis.com".into(),
+            submit: "https://open.kattis.com/submit".into(),
+        };
+        let config = ClientConfig {
+            base_url: Some(format!("http://{}", listener.local_addr().unwrap())),
+            ..ClientConfig::default()
+        };
+
+        KattisClient::new(&kattis, &config, dir, true).unwrap()
+    }
+
+    fn entry(url: &str) -> CacheEntry {
+        CacheEntry {
+            url: url.to_string(),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn stores_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HttpCache::new(&dir.path().join("cache"));
+
+        assert!(cache
+            .get("https://open.kattis.com/problems/hello")
+            .is_none());
+        cache
+            .store(&entry("https://open.kattis.com/problems/hello"), b"hello")
+            .unwrap();
+
+        let (cached, body) = cache.get("https://open.kattis.com/problems/hello").unwrap();
+        assert_eq!(cached.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(body, b"hello");
+        assert!(cache
+            .get("https://open.kattis.com/problems/other")
+            .is_none());
+    }
+
+    #[test]
+    fn serves_the_cache_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let client = offline_client(dir.path(), &listener);
+
+        let err = client.get("/problems/hello").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KahError>(),
+            Some(KahError::NotCached(_))
+        ));
+
+        let url = client.url("/problems/hello");
+        HttpCache::new(&dir.path().join("cache"))
+            .store(&entry(&url), b"hello")
+            .unwrap();
+        assert_eq!(client.get_text("/problems/hello").unwrap(), "hello");
+
+        // Nothing was sent to the server
+        assert!(listener.accept().is_err());
+    }
+}
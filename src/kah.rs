@@ -1,11 +1,29 @@
-use crate::language::problem_path;
 use crate::languages::Languages;
-use crate::{error::KahError::KattisrcParseError, problem::ProblemMetadata, ForceProblemCreation};
+use crate::{
+    cargo,
+    client::{ClientConfig, KattisClient},
+    error::KahError::{
+        KattisrcMissingKey, KattisrcParseError, NoConfig, NoDefaultProfile, NoEditor,
+        NoSuchProfile, ProfileExists, SolutionPathTaken,
+    },
+    layout::{Layout, ProblemPaths},
+    problem::ProblemMetadata,
+    secret::{self, Secret, SecretStore, SecretsConfig},
+    statement,
+    template::{read_template, TemplateContext},
+    utils::write_atomically,
+    workspace::Workspace,
+    ForceProblemCreation,
+};
 use anyhow::Result;
-use directories::ProjectDirs;
+use chrono::Local;
+use directories::{BaseDirs, ProjectDirs};
 use ini::Ini;
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, HashMap},
+    env,
     env::current_dir,
     fs,
     fs::{read_to_string, File},
@@ -13,69 +31,331 @@ use std::{
     path::{Path, PathBuf},
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct Kattis {
     pub(crate) username: String,
-    pub(crate) token: String,
+    /// Kept in the secret store, only read from older configurations
+    #[serde(default, skip_serializing)]
+    pub(crate) token: Secret,
     pub(crate) hostname: String,
     pub(crate) submit: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct KahConfig {
     pub(crate) code: PathBuf,
     pub(crate) dir: PathBuf,
     pub(crate) file: PathBuf,
+    /// Datafile of the profile in use, only read from older configurations
+    #[serde(default, skip_serializing)]
+    pub(crate) data: PathBuf,
+    #[serde(default = "default_backups")]
+    pub(crate) backups: usize,
+    #[serde(default)]
+    pub(crate) http: ClientConfig,
+    /// Language new problems are created in unless the workspace says
+    /// otherwise
+    #[serde(default)]
+    pub(crate) language: Option<Languages>,
+    #[serde(default)]
+    pub(crate) secrets: SecretsConfig,
+    /// Directory with solution templates, defaults to `templates` in the
+    /// configuration directory
+    #[serde(default)]
+    pub(crate) templates: Option<PathBuf>,
+    /// Directory of the snippet library, defaults to `snippets` in the
+    /// configuration directory
+    #[serde(default)]
+    pub(crate) snippets: Option<PathBuf>,
+    /// Snippets added to every new solution in a language
+    #[serde(default)]
+    pub(crate) default_snippets: HashMap<Languages, Vec<String>>,
+    /// Create a cargo package for every Rust problem
+    #[serde(default)]
+    pub(crate) cargo: bool,
+    /// Command to edit solutions with, `$VISUAL` or `$EDITOR` if not set
+    #[serde(default)]
+    pub(crate) editor: Option<String>,
+    /// Open new problems in the editor without `--open`
+    #[serde(default)]
+    pub(crate) open: bool,
+    /// Open the statement along with the solution
+    #[serde(default)]
+    pub(crate) open_statement: bool,
+}
+
+fn default_backups() -> usize {
+    5
+}
+
+/// Credentials for a Kattis host along with where its data is kept, so that
+/// e.g. open.kattis.com and a university instance can be used side by side.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Profile {
+    pub(crate) kattis: Kattis,
+    /// Directory for the kattisrc, cookies and cache of the profile
+    pub(crate) dir: PathBuf,
     pub(crate) data: PathBuf,
 }
 
+/// The configuration file as it is stored on disk.
 #[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ConfigFile {
+    pub(crate) config: KahConfig,
+    #[serde(default)]
+    pub(crate) default_profile: Option<String>,
+    #[serde(default)]
+    pub(crate) profiles: BTreeMap<String, Profile>,
+    /// Credentials from before there were profiles
+    #[serde(default, skip_serializing)]
+    kattis: Option<Kattis>,
+}
+
+impl ConfigFile {
+    fn path() -> PathBuf {
+        Kah::project_dir().config_dir().join("config.json")
+    }
+
+    /// Reads the configuration file, turning the credentials of older
+    /// configurations into a profile named `default`.
+    pub(crate) fn load() -> Result<Option<ConfigFile>> {
+        let path = ConfigFile::path();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut file: ConfigFile = serde_json::from_str(&read_to_string(path)?)?;
+        if let Some(kattis) = file.kattis.take() {
+            let profile = Profile {
+                kattis,
+                dir: file.config.dir.clone(),
+                data: file.config.data.clone(),
+            };
+            file.profiles
+                .entry(DEFAULT_PROFILE.to_string())
+                .or_insert(profile);
+            file.default_profile
+                .get_or_insert_with(|| DEFAULT_PROFILE.to_string());
+        }
+        if file.profiles.values().any(|p| !p.kattis.token.is_empty()) {
+            file.move_tokens()?;
+        }
+
+        Ok(Some(file))
+    }
+
+    pub(crate) fn secrets(&self) -> Box<dyn SecretStore> {
+        secret::store(&self.config.secrets, &self.config.dir)
+    }
+
+    /// Moves tokens that older versions kept in plain text, both in the
+    /// configuration and the copied kattisrc, into the secret store.
+    fn move_tokens(&mut self) -> Result<()> {
+        let secrets = self.secrets();
+        for (name, profile) in &mut self.profiles {
+            if profile.kattis.token.is_empty() {
+                continue;
+            }
+
+            secrets.set(&token_key(name), &profile.kattis.token)?;
+            profile.kattis.token = Secret::default();
+
+            let kattisrc = profile.dir.join("kattisrc");
+            if kattisrc.is_file() && !kattisrc.symlink_metadata()?.file_type().is_symlink() {
+                copy_kattisrc(&kattisrc, &kattisrc)?;
+            }
+        }
+        self.save()?;
+
+        println!("Moved your Kattis tokens to the secret store");
+        Ok(())
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.config.dir)?;
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomically(&self.config.file, json.as_bytes())
+    }
+
+    /// Picks the profile asked for, the default one, or the only one there is.
+    pub(crate) fn profile(&self, name: Option<&str>) -> Result<(String, Profile)> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(name) => name.to_string(),
+            None if self.profiles.len() == 1 => self.profiles.keys().next().cloned().unwrap(),
+            None => return Err(NoDefaultProfile.into()),
+        };
+
+        match self.profiles.get(&name) {
+            Some(profile) => Ok((name, profile.clone())),
+            None => Err(NoSuchProfile(name).into()),
+        }
+    }
+}
+
+const DEFAULT_PROFILE: &str = "default";
+
+/// Name of the token of a profile in the secret store.
+pub(crate) fn token_key(profile: &str) -> String {
+    format!("{}.token", profile)
+}
+
+#[derive(Debug)]
 pub(crate) struct Kah {
     pub(crate) config: KahConfig,
     pub(crate) kattis: Kattis,
+    pub(crate) workspace: Option<Workspace>,
+    pub(crate) client: KattisClient,
+}
+
+/// How the kattisrc of a profile is kept in the configuration directory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum KattisrcStorage {
+    Copy,
+    Symlink,
+}
+
+/// What `kah init` was told to do.
+#[derive(Debug)]
+pub(crate) struct InitOptions {
+    pub(crate) kattisrc: PathBuf,
+    pub(crate) storage: KattisrcStorage,
+    pub(crate) language: Option<Languages>,
+    pub(crate) code: Option<PathBuf>,
+    pub(crate) force: bool,
+}
+
+/// Options given on the command line that affect how `kah` is set up.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct KahOptions {
+    /// Only use cached responses from Kattis
+    pub(crate) offline: bool,
+    /// Use this profile instead of the default one
+    pub(crate) profile: Option<String>,
 }
 
 impl Kah {
-    pub(crate) fn new(kattisrc_path: PathBuf, force: bool) -> Result<Self> {
+    /// Adds a profile for the credentials in a kattisrc, creating the
+    /// configuration if there is none. The profile is named after the Kattis
+    /// host unless `--profile` is given. The kattisrc itself is left alone.
+    pub(crate) fn new(init: &InitOptions, options: &KahOptions) -> Result<Self> {
         let config_dir = Kah::project_dir().config_dir().to_owned();
-
-        let kah = Kah {
-            config: KahConfig {
-                code: current_dir()?,
-                dir: config_dir.clone(),
-                file: config_dir.join("config.json"),
-                data: config_dir.join("data.json"),
+        let mut kattis = Kah::parse_kattisrc(&init.kattisrc)?;
+        let mut file = match ConfigFile::load()? {
+            Some(file) => file,
+            None => ConfigFile {
+                config: KahConfig {
+                    code: current_dir()?,
+                    dir: config_dir.clone(),
+                    file: ConfigFile::path(),
+                    data: PathBuf::new(),
+                    backups: default_backups(),
+                    http: ClientConfig::default(),
+                    language: None,
+                    secrets: SecretsConfig::default(),
+                    templates: None,
+                    snippets: None,
+                    default_snippets: HashMap::new(),
+                    cargo: false,
+                    editor: None,
+                    open: false,
+                    open_statement: false,
+                },
+                default_profile: None,
+                profiles: BTreeMap::new(),
+                kattis: None,
             },
-            kattis: match Kah::parse_kattisrc(kattisrc_path.clone()) {
-                Some(x) => Ok(x),
-                None => Err(KattisrcParseError),
-            }?,
         };
+        if let Some(code) = &init.code {
+            file.config.code = code.clone();
+        }
+        if let Some(language) = &init.language {
+            file.config.language = Some(language.clone());
+        }
 
-        kah.create_config_dir(force)?;
-        if !kah.kattisrc_exists() {
-            fs::copy(&kattisrc_path, config_dir.join("kattisrc"))?;
-            fs::remove_file(&kattisrc_path)?;
+        let name = match &options.profile {
+            Some(name) => name.clone(),
+            None => profile_name(&kattis.hostname),
+        };
+        if file.profiles.contains_key(&name) && !init.force {
+            return Err(ProfileExists(name).into());
         }
 
-        kah.create_config_file()?;
-        kah.create_datafile(force)?;
+        let dir = config_dir.join("profiles").join(&name);
+        fs::create_dir_all(&dir)?;
+        store_kattisrc(&init.kattisrc, &dir.join("kattisrc"), init.storage)?;
+        file.secrets().set(&token_key(&name), &kattis.token)?;
+        kattis.token = Secret::default();
+
+        file.profiles.insert(
+            name.clone(),
+            Profile {
+                kattis,
+                data: dir.join("data.json"),
+                dir,
+            },
+        );
+        file.default_profile.get_or_insert_with(|| name.clone());
+        file.save()?;
+
+        let options = KahOptions {
+            profile: Some(name.clone()),
+            ..options.clone()
+        };
+        let kah = Kah::from_config(file, &options)?;
+        kah.create_datafile(init.force)?;
 
-        println!("Successfully created configuration");
+        println!("Successfully added profile {}", name);
 
         Ok(kah)
     }
 
-    pub(crate) fn get() -> Result<Self> {
-        let path = Kah::project_dir().config_dir().join("config.json");
-        let file = read_to_string(path)?;
+    /// Looks for a kattisrc in the current directory and then the home
+    /// directory, where the Kattis submission script expects it.
+    pub(crate) fn find_kattisrc() -> Option<PathBuf> {
+        let mut candidates = Vec::new();
+        if let Ok(dir) = current_dir() {
+            candidates.push(dir.join(".kattisrc"));
+        }
+        if let Some(dirs) = BaseDirs::new() {
+            candidates.push(dirs.home_dir().join(".kattisrc"));
+        }
+
+        candidates.into_iter().find(|c| c.is_file())
+    }
+
+    pub(crate) fn get(options: &KahOptions) -> Result<Self> {
+        let file = ConfigFile::load()?.ok_or(NoConfig)?;
+        let mut kah = Kah::from_config(file, options)?;
+
+        if let Some(workspace) = Workspace::find(&current_dir()?)? {
+            kah.config.code = workspace.code_dir();
+            kah.config.data = workspace.datafile();
+            kah.workspace = Some(workspace);
+        }
+
+        Ok(kah)
+    }
 
-        let result = serde_json::from_str(&file)?;
-        Ok(result)
+    fn from_config(file: ConfigFile, options: &KahOptions) -> Result<Self> {
+        let (name, profile) = file.profile(options.profile.as_deref())?;
+        let secrets = file.secrets();
+        let mut config = file.config;
+        config.data = profile.data;
+
+        let client =
+            KattisClient::new(&profile.kattis, &config.http, &profile.dir, options.offline)?
+                .with_secrets(secrets, token_key(&name));
+
+        Ok(Kah {
+            config,
+            kattis: profile.kattis,
+            workspace: None,
+            client,
+        })
     }
 
     pub(crate) fn get_kattis_url(&self) -> String {
-        self.kattis.hostname.to_string()
+        self.client.base_url().to_string()
     }
 
     pub(crate) fn create_problem(
@@ -84,58 +364,176 @@ impl Kah {
         language: Languages,
         force: ForceProblemCreation,
     ) -> Result<()> {
-        let code = language.initial_problem_content();
-        let path = problem_path(&language, &problem);
+        let paths = self.layout()?.paths(problem, &language, "");
+        self.create_problem_at(problem, language, &paths, force)
+    }
+
+    /// The configured editor, or the one in `$VISUAL` or `$EDITOR`.
+    pub(crate) fn editor(&self) -> Result<String> {
+        self.config
+            .editor
+            .clone()
+            .or_else(|| env::var("VISUAL").ok())
+            .or_else(|| env::var("EDITOR").ok())
+            .filter(|e| !e.trim().is_empty())
+            .ok_or_else(|| NoEditor.into())
+    }
+
+    /// Whether Rust problems get a cargo package, which the workspace can
+    /// decide.
+    fn cargo_packages(&self) -> bool {
+        self.workspace
+            .as_ref()
+            .and_then(|w| w.cargo)
+            .unwrap_or(self.config.cargo)
+    }
+
+    /// The layout of the workspace, or every solution of a language in one
+    /// folder outside of workspaces.
+    pub(crate) fn layout(&self) -> Result<Layout> {
+        match &self.workspace {
+            Some(workspace) => workspace.layout(),
+            None => Ok(Layout::default()),
+        }
+    }
+
+    /// Creates a problem with its files at `paths`, relative to the code
+    /// directory.
+    pub(crate) fn create_problem_at(
+        &mut self,
+        problem: &ProblemMetadata,
+        language: Languages,
+        paths: &ProblemPaths,
+        force: ForceProblemCreation,
+    ) -> Result<()> {
+        let paths = &match language {
+            Languages::Rust if self.cargo_packages() => cargo::in_package(paths.clone()),
+            _ => paths.clone(),
+        };
+        let taken = self
+            .problems()?
+            .into_iter()
+            .find(|p| p.metadata.id != problem.id && p.solution_path() == paths.solution);
+        if let Some(other) = taken {
+            return Err(
+                SolutionPathTaken(paths.solution.display().to_string(), other.metadata.id).into(),
+            );
+        }
 
-        let language_folder = &language.language_path();
+        let url = format!("{}/problems/{}", self.get_kattis_url(), problem.id);
+        let context =
+            TemplateContext::new(problem, &url, &paths.solution, Local::now().date_naive());
+        let code = context.render(&self.template(&language)?);
+        let code = self.insert_default_snippets(&language, code);
+        let path = self.config.code.join(&paths.solution);
 
-        if !Path::new(language_folder).exists() {
-            fs::create_dir_all(language_folder)?;
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
         }
 
-        let path = Path::new(&path);
         if path.exists() && !force.recreate_solution() {
             eprintln!(
                 "{} already exists for language {}, skipping code creation",
-                problem.name,
-                language.to_string()
+                problem.name, language
             );
         } else {
-            let mut file = File::create(path)?;
+            let mut file = File::create(&path)?;
             file.write_all(code.as_bytes())?;
         }
+        if language == Languages::Rust && self.cargo_packages() {
+            cargo::create_package(&path)?;
+        }
 
-        println!("Created {} in {}", problem.name, language.to_string());
-
-        self.add_problem(problem, &language, force)?;
-
-        Ok(())
-    }
+        let statement_path = self.config.code.join(&paths.statement);
+        if let Some(folder) = statement_path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        if statement_path.exists() && !force.recreate_metadata() {
+            eprintln!(
+                "Statement for {} already exists, skipping statement creation",
+                problem.name
+            );
+        } else {
+            self.create_statement(problem, &statement_path)?;
+        }
 
-    fn create_config_file(&self) -> Result<()> {
-        let mut file = File::create(&self.config.file)?;
+        if let Some(notes) = &paths.notes {
+            let notes = self.config.code.join(notes);
+            if !notes.exists() {
+                fs::write(notes, format!("# {}\n", problem.name))?;
+            }
+        }
+        if let Some(tests) = &paths.tests {
+            fs::create_dir_all(self.config.code.join(tests))?;
+        }
 
-        let json = serde_json::to_string_pretty(self)?;
-        let buffer = json.into_bytes();
+        println!("Created {} in {}", problem.name, language);
 
-        file.write_all(&buffer)?;
+        self.add_problem(problem, &language, paths, force)?;
 
         Ok(())
     }
 
-    fn config_exists(&self) -> bool {
-        self.config.dir.exists() || self.config.file.exists()
-    }
+    /// Finds the template for new solutions, preferring the workspace, then
+    /// the templates directory in the configuration, then the built in one.
+    fn template(&self, language: &Languages) -> Result<String> {
+        if let Some(workspace) = &self.workspace {
+            if let Some(template) = workspace.template(language)? {
+                return Ok(template);
+            }
+        }
 
-    fn kattisrc_exists(&self) -> bool {
-        self.config.dir.join("kattisrc").exists()
+        let dir = match &self.config.templates {
+            Some(dir) => dir.clone(),
+            None => self.config.dir.join("templates"),
+        };
+        match read_template(&dir, language)? {
+            Some(template) => Ok(template),
+            None => Ok(language.initial_problem_content()),
+        }
     }
 
-    fn create_config_dir(&self, force: bool) -> Result<()> {
-        if !self.config_exists() && !force {
-            fs::create_dir_all(&self.config.dir)?;
+    /// Writes the statement of a problem as Markdown, downloading its images
+    /// next to it. Images that cannot be downloaded are linked to on Kattis
+    /// instead, so they never stop a problem from being created.
+    fn create_statement(&self, problem: &ProblemMetadata, path: &Path) -> Result<()> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let base = Url::parse(&format!(
+            "{}/problems/{}/",
+            self.get_kattis_url(),
+            problem.id
+        ))?;
+
+        let mut images = HashMap::new();
+        if let Some(statement) = &problem.statement {
+            for src in statement::image_sources(statement) {
+                let url = match base.join(&src) {
+                    Ok(url) => url,
+                    Err(_) => continue,
+                };
+                let name = match url.path_segments().and_then(|mut s| s.next_back()) {
+                    Some(name) if !name.is_empty() => name.to_string(),
+                    _ => continue,
+                };
+                let name = format!("{}-{}", problem.as_os_str(), name);
+
+                let downloaded = self
+                    .client
+                    .get(url.as_str())
+                    .and_then(|image| Ok(fs::write(dir.join(&name), image)?));
+                match downloaded {
+                    Ok(()) => images.insert(src, name),
+                    Err(err) => {
+                        eprintln!("Could not download image {}: {}", url, err);
+                        images.insert(src, url.to_string())
+                    }
+                };
+            }
         }
 
+        let markdown = problem.to_markdown(base.as_str().trim_end_matches('/'), &images);
+        fs::write(path, markdown)?;
+
         Ok(())
     }
 
@@ -144,28 +542,127 @@ impl Kah {
             .expect("Could not create project dir identifier")
     }
 
-    fn parse_kattisrc(path: PathBuf) -> Option<Kattis> {
-        let file = Ini::load_from_file(path).ok()?;
+    /// Reads the credentials from a kattisrc, naming the key that is missing
+    /// if it is incomplete.
+    pub(crate) fn parse_kattisrc(path: &Path) -> Result<Kattis> {
+        let file = Ini::load_from_file(path)
+            .map_err(|e| KattisrcParseError(path.display().to_string(), e.to_string()))?;
+        let get = |section: &str, key: &str| {
+            file.section(Some(section))
+                .and_then(|s| s.get(key))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| KattisrcMissingKey(section.to_string(), key.to_string()))
+        };
+
+        let hostname = get("kattis", "hostname")?;
+        let hostname = if hostname.contains("://") {
+            hostname
+        } else {
+            format!("https://{}", hostname)
+        };
+
+        Ok(Kattis {
+            username: get("user", "username")?,
+            token: Secret::new(get("user", "token")?),
+            hostname,
+            submit: get("kattis", "submissionurl")?,
+        })
+    }
+}
 
-        let kattisrc = {
-            let user_section = file.section(Some("user"))?;
-            let kattis_section = file.section(Some("kattis"))?;
+/// Copies or links the kattisrc of a profile into place, replacing what was
+/// there before.
+fn store_kattisrc(source: &Path, target: &Path, storage: KattisrcStorage) -> Result<()> {
+    match storage {
+        KattisrcStorage::Copy => copy_kattisrc(source, target),
+        KattisrcStorage::Symlink => {
+            if target.symlink_metadata().is_ok() {
+                fs::remove_file(target)?;
+            }
+            Ok(symlink(&source.canonicalize()?, target)?)
+        }
+    }
+}
 
-            let username = user_section.get("username")?;
-            let token = user_section.get("token")?;
+/// Copies a kattisrc without its token, which is kept in the secret store.
+fn copy_kattisrc(source: &Path, target: &Path) -> Result<()> {
+    let mut kattisrc = Ini::load_from_file(source)
+        .map_err(|e| KattisrcParseError(source.display().to_string(), e.to_string()))?;
+    kattisrc.delete_from(Some("user"), "token");
 
-            let submit = kattis_section.get("submissionurl")?;
-            let hostname = kattis_section.get("hostname")?;
-            let hostname = format!("{}{}", "https://", hostname);
+    let mut content = Vec::new();
+    kattisrc.write_to(&mut content)?;
+    write_atomically(target, &content)
+}
 
-            Some(Kattis {
-                username: username.into(),
-                token: token.into(),
-                hostname,
-                submit: submit.to_owned(),
-            })
-        };
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(source, target)
+}
+
+/// Names a profile after its host, e.g. `open` for open.kattis.com.
+fn profile_name(hostname: &str) -> String {
+    let host = hostname
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+
+    match host.split('.').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => DEFAULT_PROFILE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::KahError;
+
+    fn kattisrc(content: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".kattisrc");
+        fs::write(&path, content).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn parses_kattisrc() {
+        let (_dir, path) = kattisrc(
+            "[user]\nusername: me\ntoken: abc\n\n[kattis]\nhostname: open.kattis.com\nsubmissionurl: https://open.kattis.com/submit\n",
+        );
+        let kattis = Kah::parse_kattisrc(&path).unwrap();
+
+        assert_eq!(kattis.username, "me");
+        assert_eq!(kattis.token.expose(), "abc");
+        assert_eq!(kattis.hostname, "https://open.kattis.com");
+        assert_eq!(profile_name(&kattis.hostname), "open");
+    }
+
+    #[test]
+    fn copies_kattisrc_without_token() {
+        let (dir, path) = kattisrc("[user]\nusername: me\ntoken: abc\n");
+        let copy = dir.path().join("kattisrc");
+        copy_kattisrc(&path, &copy).unwrap();
+
+        let copy = fs::read_to_string(copy).unwrap();
+        assert!(copy.contains("username"));
+        assert!(!copy.contains("abc"));
+    }
+
+    #[test]
+    fn reports_missing_kattisrc_keys() {
+        let (_dir, path) =
+            kattisrc("[user]\nusername: me\n\n[kattis]\nhostname: open.kattis.com\n");
+        let err = Kah::parse_kattisrc(&path).unwrap_err();
 
-        kattisrc
+        assert_eq!(
+            err.downcast::<KahError>().unwrap(),
+            KahError::KattisrcMissingKey("user".into(), "token".into())
+        );
     }
 }
@@ -1,26 +1,22 @@
-use crate::language::problem_path;
 use crate::{
-    language::run_problem,
+    language::{compile, run_problem},
     test::{Test, TestResult},
 };
 use anyhow::Result;
-use std::process::{Command, Stdio};
+use std::process::Command;
 
-pub(crate) fn build_cpp() -> Result<()> {
-    Command::new("cmake --build .")
-        .stdout(Stdio::piped())
-        .stdin(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+/// Compiles the solution like Kattis does, with optimizations and the GNU
+/// dialect of C++17.
+pub(crate) fn build_cpp(test: &Test) -> Result<()> {
+    let mut command = Command::new("g++");
+    command
+        .args(["-O2", "-std=gnu++17", "-o"])
+        .arg(test.binary())
+        .arg(&test.file);
 
-    Ok(())
+    compile(command, test)
 }
 
 pub(crate) fn run_cpp(test: &Test) -> Result<TestResult> {
-    let root = &test.code_dir;
-    let language = &test.problem.solution.language;
-    let path = problem_path(language, &test.problem.metadata);
-    let file = root.join(path);
-
-    run_problem("./", &file, test)
+    run_problem(test.binary(), &[], test)
 }
@@ -1,11 +1,16 @@
 pub(crate) mod cpp;
 pub(crate) mod python;
+pub(crate) mod rust;
 
-use crate::{languages::Languages, problem::ProblemMetadata, test::Test, test::TestResult};
+use crate::{
+    error::KahError::CompileError, languages::Languages, problem::ProblemMetadata, test::Test,
+    test::TestResult,
+};
 use anyhow::Result;
 use std::{
+    ffi::OsStr,
     io::Write,
-    path::PathBuf,
+    path::Path,
     process::{Command, Stdio},
     time::Instant,
 };
@@ -19,13 +24,34 @@ pub(crate) fn problem_path(lang: &Languages, problem: &ProblemMetadata) -> Strin
     )
 }
 
-pub(crate) fn run_problem(command: &str, file: &PathBuf, test: &Test) -> Result<TestResult> {
+/// Runs a compiler, failing with its output if the solution does not compile.
+pub(crate) fn compile(mut command: Command, test: &Test) -> Result<()> {
+    let output = command.stdin(Stdio::null()).output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(CompileError(
+            test.file.display().to_string(),
+            String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string(),
+        )
+        .into())
+    }
+}
+
+pub(crate) fn run_problem<S: AsRef<OsStr>>(
+    program: S,
+    args: &[&Path],
+    test: &Test,
+) -> Result<TestResult> {
     let mut result = TestResult::new();
 
     for case in &test.problem.metadata.samples {
         let before = Instant::now();
-        let mut command = Command::new(command)
-            .arg(file)
+        let mut command = Command::new(&program)
+            .args(args)
             .stdout(Stdio::piped())
             .stdin(Stdio::piped())
             .stderr(Stdio::piped())
@@ -0,0 +1,64 @@
+use crate::{
+    cargo::{manifest, KATTIS_EDITION},
+    error::KahError::CompileError,
+    language::{compile, run_problem},
+    test::{Test, TestResult},
+};
+use anyhow::Result;
+use serde_json::Value;
+use std::{fs, path::Path, process::Command};
+
+/// Solutions in a cargo package are built with cargo, others with rustc.
+pub(crate) fn build_rust(test: &Test) -> Result<()> {
+    if let Some(manifest) = manifest(&test.file) {
+        return build_cargo(&manifest, test);
+    }
+
+    let mut command = Command::new("rustc");
+    command
+        .args(["--edition", KATTIS_EDITION, "-O", "-o"])
+        .arg(test.binary())
+        .arg(&test.file);
+
+    compile(command, test)
+}
+
+/// Builds the package in release mode and copies the executable to where
+/// the tests run it from.
+fn build_cargo(manifest: &Path, test: &Test) -> Result<()> {
+    let output = Command::new("cargo")
+        .args([
+            "build",
+            "--release",
+            "--message-format=json-render-diagnostics",
+        ])
+        .arg("--manifest-path")
+        .arg(manifest)
+        .output()?;
+    let failed = || {
+        CompileError(
+            test.file.display().to_string(),
+            String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string(),
+        )
+    };
+    if !output.status.success() {
+        return Err(failed().into());
+    }
+
+    let executable = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| message["reason"] == "compiler-artifact")
+        .filter_map(|message| message["executable"].as_str().map(str::to_string))
+        .next_back()
+        .ok_or_else(failed)?;
+    fs::copy(executable, test.binary())?;
+
+    Ok(())
+}
+
+pub(crate) fn run_rust(test: &Test) -> Result<TestResult> {
+    run_problem(test.binary(), &[], test)
+}
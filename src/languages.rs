@@ -2,12 +2,14 @@ use crate::{
     error::KahError,
     language::cpp::{build_cpp, run_cpp},
     language::python::run_python,
+    language::rust::{build_rust, run_rust},
     test::{Test, TestResult},
 };
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Formatter},
+    path::Path,
     str::FromStr,
 };
 
@@ -18,7 +20,8 @@ const RUST_CODE: &str = include_str!("./data/Problem.rs");
 const HASKELL_CODE: &str = include_str!("./data/Problem.hs");
 const CPP_CODE: &str = include_str!("./data/Problem.cpp");
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Languages {
     Python,
     Java,
@@ -60,19 +63,32 @@ impl FromStr for Languages {
 }
 
 impl Languages {
-    pub(crate) fn build(&self) -> Result<()> {
+    pub(crate) fn all() -> Vec<Languages> {
+        vec![
+            Languages::Python,
+            Languages::Java,
+            Languages::Haskell,
+            Languages::Rust,
+            Languages::Kotlin,
+            Languages::CPP,
+        ]
+    }
+
+    pub(crate) fn build(&self, test: &Test) -> Result<()> {
         match self {
-            Languages::CPP => build_cpp(),
+            Languages::CPP => build_cpp(test),
+            Languages::Rust => build_rust(test),
             Languages::Python => Ok(()),
-            _ => todo!(),
+            _ => Err(KahError::CannotTest(self.to_string()).into()),
         }
     }
 
     pub(crate) fn run(&self, test: &Test) -> Result<TestResult> {
         match self {
             Languages::CPP => run_cpp(test),
+            Languages::Rust => run_rust(test),
             Languages::Python => run_python(test),
-            _ => todo!(),
+            _ => Err(KahError::CannotTest(self.to_string()).into()),
         }
     }
 
@@ -92,6 +108,49 @@ impl Languages {
         .to_string()
     }
 
+    /// How line comments start.
+    pub(crate) fn comment(&self) -> &'static str {
+        match self {
+            Languages::Python => "#",
+            Languages::Haskell => "--",
+            Languages::Java | Languages::Rust | Languages::Kotlin | Languages::CPP => "//",
+        }
+    }
+
+    /// Name of the language when submitting to Kattis.
+    pub(crate) fn kattis_name(&self) -> String {
+        match self {
+            Languages::Python => "Python 3",
+            Languages::Java => "Java",
+            Languages::Haskell => "Haskell",
+            Languages::Rust => "Rust",
+            Languages::Kotlin => "Kotlin",
+            Languages::CPP => "C++",
+        }
+        .to_string()
+    }
+
+    /// The class Kattis runs for languages on the JVM, empty for the rest.
+    /// Kotlin capitalizes the file name, e.g. `main.kt` becomes `MainKt`.
+    pub(crate) fn main_class(&self, file: &Path) -> String {
+        let stem = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        match self {
+            Languages::Java => stem,
+            Languages::Kotlin => {
+                let mut chars = stem.chars();
+                match chars.next() {
+                    Some(first) => format!("{}{}Kt", first.to_uppercase(), chars.as_str()),
+                    None => String::new(),
+                }
+            }
+            _ => String::new(),
+        }
+    }
+
     pub(crate) fn initial_problem_content(&self) -> String {
         match self {
             Languages::Python => PYTHON_CODE,
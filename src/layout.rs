@@ -0,0 +1,179 @@
+use crate::{error::KahError::UnknownLayout, languages::Languages, problem::ProblemMetadata};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Where the files of a new problem go, relative to the code directory. The
+/// paths are patterns where `{id}`, `{name}`, `{language}` and `{ext}` are
+/// replaced by the id and name of the problem and the language and its file
+/// extension.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Layout {
+    pub(crate) solution: String,
+    /// Defaults to a Markdown file next to the solution
+    pub(crate) statement: Option<String>,
+    /// A file for notes, created empty
+    pub(crate) notes: Option<String>,
+    /// Directory for extra test cases, in `<name>.{in,ans}`
+    pub(crate) tests: Option<String>,
+}
+
+impl Default for Layout {
+    /// Every solution of a language in one folder, e.g. `rust/HelloWorld.rs`.
+    fn default() -> Self {
+        Layout {
+            solution: "{language}/{name}.{ext}".to_string(),
+            statement: None,
+            notes: None,
+            tests: None,
+        }
+    }
+}
+
+impl Layout {
+    /// A folder per problem, e.g. `hello/main.rs` with `hello/tests/`.
+    fn per_problem() -> Self {
+        Layout {
+            solution: "{id}/main.{ext}".to_string(),
+            statement: Some("{id}/statement.md".to_string()),
+            notes: Some("{id}/notes.md".to_string()),
+            tests: Some("{id}/tests".to_string()),
+        }
+    }
+
+    /// Finds a layout by its name, `flat` or `problem`.
+    pub(crate) fn named(name: &str) -> Result<Self> {
+        match name {
+            "flat" => Ok(Layout::default()),
+            "problem" => Ok(Layout::per_problem()),
+            _ => Err(UnknownLayout(name.to_string()).into()),
+        }
+    }
+
+    /// The paths of a problem, where `prefix` goes in front of the id and
+    /// name, e.g. the letter of a problem in a contest.
+    pub(crate) fn paths(
+        &self,
+        problem: &ProblemMetadata,
+        language: &Languages,
+        prefix: &str,
+    ) -> ProblemPaths {
+        let expand = |pattern: &str| {
+            PathBuf::from(
+                pattern
+                    .replace("{id}", &format!("{}{}", prefix, problem.id))
+                    .replace("{name}", &format!("{}{}", prefix, problem.as_os_str()))
+                    .replace("{language}", &language.language_path())
+                    .replace("{ext}", &language.extension()),
+            )
+        };
+
+        let solution = expand(&self.solution);
+        ProblemPaths {
+            statement: match &self.statement {
+                Some(statement) => expand(statement),
+                None => solution.with_extension("md"),
+            },
+            notes: self.notes.as_deref().map(expand),
+            tests: self.tests.as_deref().map(expand),
+            solution,
+        }
+    }
+}
+
+/// The files of a problem, relative to the code directory.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct ProblemPaths {
+    pub(crate) solution: PathBuf,
+    pub(crate) statement: PathBuf,
+    pub(crate) notes: Option<PathBuf>,
+    pub(crate) tests: Option<PathBuf>,
+}
+
+impl ProblemPaths {
+    /// Moves every path into `dir`.
+    pub(crate) fn within(self, dir: &Path) -> Self {
+        ProblemPaths {
+            solution: dir.join(self.solution),
+            statement: dir.join(self.statement),
+            notes: self.notes.map(|n| dir.join(n)),
+            tests: self.tests.map(|t| dir.join(t)),
+        }
+    }
+}
+
+/// A layout in `kah.toml`, either the name of a built in one or the patterns.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub(crate) enum LayoutConfig {
+    Named(String),
+    Custom(Layout),
+}
+
+impl LayoutConfig {
+    pub(crate) fn layout(&self) -> Result<Layout> {
+        match self {
+            LayoutConfig::Named(name) => Layout::named(name),
+            LayoutConfig::Custom(layout) => Ok(layout.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem() -> ProblemMetadata {
+        ProblemMetadata {
+            name: "Hello World!".to_string(),
+            id: "hello".to_string(),
+            cpu_time_limit: "1 second".to_string(),
+            memory_limit: "1024 MB".to_string(),
+            difficulty: 1.2,
+            samples: Vec::new(),
+            statement: None,
+        }
+    }
+
+    #[test]
+    fn expands_layouts() {
+        let flat = Layout::default().paths(&problem(), &Languages::Rust, "");
+        assert_eq!(flat.solution, PathBuf::from("rust/HelloWorld.rs"));
+        assert_eq!(flat.statement, PathBuf::from("rust/HelloWorld.md"));
+        assert_eq!(flat.tests, None);
+
+        let per_problem =
+            Layout::named("problem")
+                .unwrap()
+                .paths(&problem(), &Languages::Python, "A_");
+        assert_eq!(
+            per_problem,
+            ProblemPaths {
+                solution: PathBuf::from("A_hello/main.py"),
+                statement: PathBuf::from("A_hello/statement.md"),
+                notes: Some(PathBuf::from("A_hello/notes.md")),
+                tests: Some(PathBuf::from("A_hello/tests")),
+            }
+        );
+    }
+
+    #[test]
+    fn reads_layouts_from_toml() {
+        #[derive(Deserialize)]
+        struct Config {
+            layout: LayoutConfig,
+        }
+
+        let named: Config = toml::from_str("layout = \"problem\"").unwrap();
+        assert_eq!(named.layout.layout().unwrap(), Layout::per_problem());
+
+        let custom: Config = toml::from_str("[layout]\nsolution = \"{id}.{ext}\"").unwrap();
+        let layout = custom.layout.layout().unwrap();
+        assert_eq!(layout.solution, "{id}.{ext}");
+        assert_eq!(layout.notes, None);
+
+        let unknown: Config = toml::from_str("layout = \"nested\"").unwrap();
+        assert!(unknown.layout.layout().is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::languages::Languages;
+use std::fmt::{self, Formatter};
+
+/// Code that is likely left over from debugging, like `dbg!` or printing to
+/// stderr in a loop, which is at best slow on Kattis.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Leftover {
+    /// Line number, starting at one
+    pub(crate) line: usize,
+    pub(crate) reason: &'static str,
+}
+
+impl fmt::Display for Leftover {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+struct Patterns {
+    /// Always left over from debugging
+    debug: &'static [&'static str],
+    /// Only a problem inside loops
+    in_loops: &'static [&'static str],
+    loops: &'static [&'static str],
+}
+
+fn patterns(language: &Languages) -> Patterns {
+    match language {
+        Languages::Rust => Patterns {
+            debug: &["dbg!("],
+            in_loops: &["eprintln!(", "eprint!("],
+            loops: &["for ", "while ", "loop "],
+        },
+        Languages::CPP => Patterns {
+            debug: &["#define DEBUG"],
+            in_loops: &["cerr", "fprintf(stderr"],
+            loops: &["for (", "for(", "while (", "while("],
+        },
+        Languages::Python => Patterns {
+            debug: &["breakpoint()", "pdb.set_trace()"],
+            in_loops: &["file=sys.stderr"],
+            loops: &["for ", "while "],
+        },
+        Languages::Java | Languages::Kotlin => Patterns {
+            debug: &[],
+            in_loops: &["System.err.print"],
+            loops: &["for (", "for(", "while (", "while("],
+        },
+        Languages::Haskell => Patterns {
+            debug: &["Debug.Trace"],
+            in_loops: &[],
+            loops: &[],
+        },
+    }
+}
+
+/// Finds debugging leftovers in a solution. Loops are found by their braces,
+/// or by indentation in Python.
+pub(crate) fn debug_leftovers(code: &str, language: &Languages) -> Vec<Leftover> {
+    let patterns = patterns(language);
+    let mut leftovers = Vec::new();
+    // Brace depth inside the loops we are in, or the indentation of the
+    // loops in Python
+    let mut loops: Vec<usize> = Vec::new();
+    let mut depth = 0;
+
+    for (number, line) in code.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let level = match language {
+            Languages::Python => line.len() - line.trim_start().len(),
+            _ => depth,
+        };
+        match language {
+            Languages::Python => loops.retain(|start| *start < level),
+            _ => loops.retain(|inside| *inside <= level),
+        }
+
+        if let Some(pattern) = patterns.debug.iter().find(|p| trimmed.contains(*p)) {
+            leftovers.push(Leftover {
+                line: number + 1,
+                reason: debug_reason(pattern),
+            });
+        } else if !loops.is_empty() && patterns.in_loops.iter().any(|p| trimmed.contains(p)) {
+            leftovers.push(Leftover {
+                line: number + 1,
+                reason: "printing to stderr in a loop",
+            });
+        }
+
+        if patterns.loops.iter().any(|p| trimmed.starts_with(p)) {
+            match language {
+                Languages::Python => loops.push(level),
+                _ => loops.push(level + 1),
+            }
+        }
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+    }
+
+    leftovers
+}
+
+fn debug_reason(pattern: &str) -> &'static str {
+    match pattern {
+        "dbg!(" => "`dbg!`",
+        "#define DEBUG" => "`#define DEBUG`",
+        "Debug.Trace" => "`Debug.Trace`",
+        _ => "a breakpoint",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_rust_leftovers() {
+        let code = "fn main() {\n    eprintln!(\"start\");\n    for i in 0..10 {\n        \
+                    if i > 5 {\n            eprintln!(\"{}\", i);\n        }\n    }\n    \
+                    eprintln!(\"done\");\n    dbg!(1);\n}\n";
+
+        assert_eq!(
+            debug_leftovers(code, &Languages::Rust),
+            vec![
+                Leftover {
+                    line: 5,
+                    reason: "printing to stderr in a loop"
+                },
+                Leftover {
+                    line: 9,
+                    reason: "`dbg!`"
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_python_and_cpp_leftovers() {
+        let python = "import sys\nfor line in sys.stdin:\n    print(line, file=sys.stderr)\n\
+                      print(1, file=sys.stderr)\n";
+        let cpp =
+            "#define DEBUG\nint main() {\n  while (true) {\n    cerr << 1;\n  }\n  cerr << 2;\n}\n";
+
+        let lines = |code, language| {
+            debug_leftovers(code, &language)
+                .iter()
+                .map(|l| l.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(python, Languages::Python), vec![3]);
+        assert_eq!(lines(cpp, Languages::CPP), vec![1, 4]);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{datafile::Problem, error::KahError, languages::Languages, utils::table};
+use std::{cmp::Ordering, str::FromStr};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortBy {
+    Difficulty,
+    Name,
+    Touched,
+}
+
+impl FromStr for SortBy {
+    type Err = KahError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "difficulty" | "d" => Ok(SortBy::Difficulty),
+            "name" | "n" => Ok(SortBy::Name),
+            "touched" | "t" => Ok(SortBy::Touched),
+            _ => Err(KahError::SortParseError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ListFilter {
+    pub(crate) solved: Option<bool>,
+    pub(crate) language: Option<Languages>,
+    pub(crate) min_difficulty: Option<f32>,
+    pub(crate) max_difficulty: Option<f32>,
+}
+
+impl ListFilter {
+    pub(crate) fn matches(&self, problem: &Problem) -> bool {
+        let difficulty = problem.metadata.difficulty;
+
+        self.solved.is_none_or(|s| problem.solution.solved == s)
+            && self
+                .language
+                .as_ref()
+                .is_none_or(|l| &problem.solution.language == l)
+            && self.min_difficulty.is_none_or(|d| difficulty >= d)
+            && self.max_difficulty.is_none_or(|d| difficulty <= d)
+    }
+}
+
+pub(crate) fn sort_problems(problems: &mut [Problem], sort: SortBy) {
+    problems.sort_by(|p, o| {
+        let order = match sort {
+            SortBy::Difficulty => p
+                .metadata
+                .difficulty
+                .partial_cmp(&o.metadata.difficulty)
+                .unwrap_or(Ordering::Equal),
+            SortBy::Name => p.metadata.name.cmp(&o.metadata.name),
+            // Most recently touched problems first, untouched ones last
+            SortBy::Touched => o.solution.touched.cmp(&p.solution.touched),
+        };
+
+        order.then_with(|| p.metadata.id.cmp(&o.metadata.id))
+    });
+}
+
+pub(crate) fn render_table(problems: &[Problem]) -> String {
+    let header = [
+        "ID",
+        "Name",
+        "Language",
+        "Solved",
+        "Difficulty",
+        "CPU",
+        "Memory",
+        "Touched",
+    ];
+
+    let rows: Vec<Vec<String>> = problems
+        .iter()
+        .map(|p| {
+            vec![
+                p.metadata.id.clone(),
+                p.metadata.name.clone(),
+                p.solution.language.to_string(),
+                if p.solution.solved { "yes" } else { "no" }.to_string(),
+                format!("{:.1}", p.metadata.difficulty),
+                p.metadata.cpu_time_limit.clone(),
+                p.metadata.memory_limit.clone(),
+                match p.solution.touched {
+                    Some(touched) => touched.format("%Y-%m-%d %H:%M").to_string(),
+                    None => "-".to_string(),
+                },
+            ]
+        })
+        .collect();
+
+    table(&header, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{datafile::Solution, problem::ProblemMetadata};
+    use chrono::{TimeZone, Utc};
+
+    fn problem(id: &str, difficulty: f32, solved: bool, touched: Option<i64>) -> Problem {
+        Problem {
+            metadata: ProblemMetadata {
+                name: id.to_uppercase(),
+                id: id.to_string(),
+                cpu_time_limit: "1 second".to_string(),
+                memory_limit: "1024 MB".to_string(),
+                difficulty,
+                samples: Vec::new(),
+                statement: None,
+            },
+            solution: Solution {
+                language: if solved {
+                    Languages::Rust
+                } else {
+                    Languages::Python
+                },
+                solved,
+                touched: touched.map(|t| Utc.timestamp_opt(t, 0).unwrap()),
+                solved_at: None,
+                path: None,
+                tested: None,
+                tests: None,
+                statement: None,
+            },
+        }
+    }
+
+    fn ids(problems: &[Problem]) -> Vec<&str> {
+        problems.iter().map(|p| p.metadata.id.as_str()).collect()
+    }
+
+    #[test]
+    fn filters_problems() {
+        let problems = [
+            problem("a", 1.5, true, None),
+            problem("b", 2.5, false, None),
+            problem("c", 3.5, true, None),
+        ];
+        let matching = |filter: ListFilter| -> Vec<Problem> {
+            problems
+                .iter()
+                .filter(|p| filter.matches(p))
+                .cloned()
+                .collect()
+        };
+
+        let solved = ListFilter {
+            solved: Some(true),
+            language: None,
+            min_difficulty: None,
+            max_difficulty: Some(3.0),
+        };
+        assert_eq!(ids(&matching(solved)), vec!["a"]);
+
+        let python = ListFilter {
+            solved: None,
+            language: Some(Languages::Python),
+            min_difficulty: Some(2.5),
+            max_difficulty: None,
+        };
+        assert_eq!(ids(&matching(python)), vec!["b"]);
+    }
+
+    #[test]
+    fn sorts_problems() {
+        let mut problems = vec![
+            problem("b", 2.0, false, None),
+            problem("c", 1.0, false, Some(10)),
+            problem("a", 2.0, false, Some(20)),
+        ];
+
+        sort_problems(&mut problems, SortBy::Difficulty);
+        assert_eq!(ids(&problems), vec!["c", "a", "b"]);
+        sort_problems(&mut problems, SortBy::Name);
+        assert_eq!(ids(&problems), vec!["a", "b", "c"]);
+        sort_problems(&mut problems, "t".parse().unwrap());
+        assert_eq!(ids(&problems), vec!["a", "c", "b"]);
+
+        assert_eq!("D".parse::<SortBy>().unwrap(), SortBy::Difficulty);
+        assert!("size".parse::<SortBy>().is_err());
+    }
+}
@@ -1,22 +1,54 @@
+mod bundle;
+mod cargo;
+mod client;
+mod contest;
 mod datafile;
 mod error;
+mod http;
 mod kah;
 mod language;
 mod languages;
+mod layout;
+mod leftovers;
+mod list;
 mod problem;
+mod random;
+mod secret;
+mod snippet;
+mod statement;
+mod stats;
+mod status;
+mod submit;
+mod template;
 mod test;
 mod utils;
+mod workspace;
 
 use crate::{
-    error::KahError::{self, ForceProblemCreationError, NoSuchProblem},
-    kah::Kah,
+    bundle::bundle,
+    client::{ClientConfig, KattisClient},
+    contest::Contest,
+    datafile::Problem,
+    error::KahError::{
+        self, ForceProblemCreationError, NoBackups, NoConfig, NoContest, NoKattisrc,
+        NoRandomProblem, NoStatement, NoSuchProblem, NoSuchProfile,
+    },
+    kah::{ConfigFile, InitOptions, Kah, KahOptions, Kattis, KattisrcStorage},
     languages::Languages,
+    list::{render_table, sort_problems, ListFilter, SortBy},
     problem::ProblemMetadata,
+    random::{choose, RandomFilter},
+    secret::Secret,
+    stats::Stats,
+    status::ContestStatus,
+    submit::submit,
     test::Test,
+    utils::{edit, open_url, page},
 };
 use anyhow::Result;
-use dialoguer::{theme::ColorfulTheme, Select};
-use std::{convert::TryFrom, path::PathBuf, str::FromStr};
+use chrono::Local;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password, Select};
+use std::{convert::TryFrom, env, fs, path::PathBuf, str::FromStr, thread::sleep, time::Duration};
 use structopt::{clap::AppSettings, StructOpt};
 
 #[derive(StructOpt, PartialEq, Debug)]
@@ -28,6 +60,12 @@ pub struct Opt {
     #[structopt(short, long)]
     /// Overwrite existing files
     force: bool,
+    #[structopt(long, global = true)]
+    /// Only use cached data from Kattis, never go online
+    offline: bool,
+    #[structopt(long, global = true)]
+    /// Use this profile instead of the default one
+    profile: Option<String>,
     #[structopt(subcommand)]
     pub cmd: Cmd,
 }
@@ -44,6 +82,25 @@ pub enum Cmd {
         /// samples, `-ff` will recreate the .kahdata entry and `-fff` will
         /// recreate everything including the solution
         force: u64,
+        #[structopt(short, long)]
+        /// Open the solution in your editor
+        open: bool,
+    },
+
+    #[structopt(
+        name = "contest",
+        alias = "c",
+        setting = AppSettings::ArgsNegateSubcommands
+    )]
+    /// Get every problem of a contest or course session from Kattis
+    Contest {
+        /// Contest ID or URL of its problem list
+        contest: Option<String>,
+        #[structopt(short, parse(from_occurrences))]
+        /// Force creation of problem files, works like for `problem`
+        force: u64,
+        #[structopt(subcommand)]
+        cmd: Option<ContestCmd>,
     },
 
     #[structopt(name = "test", alias = "t")]
@@ -60,10 +117,45 @@ pub enum Cmd {
     /// Submit your solution to a Kattis problem
     Submit {
         /// Kattis problem to submit
-        file: String,
+        problem: String,
+        #[structopt(short, long)]
+        /// Submit as another language than the problem was created in
+        language: Option<Languages>,
+        #[structopt(short, long)]
+        /// Submit even if the solution fails its tests
+        force: bool,
+    },
+
+    #[structopt(name = "bundle", alias = "b")]
+    /// Inline the local modules of a solution into the file that is submitted
+    Bundle {
+        /// Problem to bundle
+        problem: String,
+        #[structopt(short, long, parse(from_os_str))]
+        /// Write the bundle to a file instead of printing it
+        output: Option<PathBuf>,
+    },
+
+    #[structopt(name = "edit", alias = "e")]
+    /// Open the solution to a problem in your editor
+    Edit {
+        /// Problem to edit
+        problem: String,
         #[structopt(short, long)]
-        /// Select language for problem
-        language: Option<String>,
+        /// Open the statement as well
+        statement: bool,
+    },
+
+    #[structopt(name = "open", alias = "o")]
+    /// Open a problem or a submission on Kattis in your browser
+    Open {
+        #[structopt(required_unless = "submission")]
+        /// ID of the problem to open, does not have to be tracked, or the name
+        /// of a tracked problem
+        problem: Option<String>,
+        #[structopt(long, conflicts_with = "problem")]
+        /// ID of a submission to open instead
+        submission: Option<String>,
     },
 
     #[structopt(name = "info", alias = "i")]
@@ -71,21 +163,204 @@ pub enum Cmd {
     Info {
         /// Problem ID
         problem: String,
+        #[structopt(short, long)]
+        /// Show the problem statement
+        statement: bool,
+    },
+
+    #[structopt(name = "list", alias = "l")]
+    /// List the problems you are tracking
+    List {
+        #[structopt(long, conflicts_with = "unsolved")]
+        /// Only show solved problems
+        solved: bool,
+        #[structopt(long)]
+        /// Only show unsolved problems
+        unsolved: bool,
+        #[structopt(short, long)]
+        /// Only show problems solved in a language
+        language: Option<Languages>,
+        #[structopt(long)]
+        /// Only show problems with at least this difficulty
+        min_difficulty: Option<f32>,
+        #[structopt(long)]
+        /// Only show problems with at most this difficulty
+        max_difficulty: Option<f32>,
+        #[structopt(short, long, default_value = "name", possible_values = &["difficulty", "name", "touched", "d", "n", "t"])]
+        /// Sort problems by difficulty, name or when they were last touched
+        sort: SortBy,
+        #[structopt(long)]
+        /// Print the problems as JSON
+        json: bool,
+    },
+
+    #[structopt(name = "random", alias = "r")]
+    /// Pick a random problem you have not solved and create it
+    Random {
+        #[structopt(long)]
+        /// Only pick problems with at least this difficulty
+        min_difficulty: Option<f32>,
+        #[structopt(long)]
+        /// Only pick problems with at most this difficulty
+        max_difficulty: Option<f32>,
+        #[structopt(long)]
+        /// Only pick problems you are not tracking yet
+        untracked: bool,
+        #[structopt(long)]
+        /// Pick the same problem as everyone using this seed
+        seed: Option<String>,
+        #[structopt(long, conflicts_with = "seed")]
+        /// Pick the problem of the day, seeded by today's date
+        daily: bool,
+        #[structopt(long)]
+        /// Fetch the problem list again even if it was cached recently
+        refresh: bool,
+        #[structopt(short, long)]
+        /// Open the solution in your editor
+        open: bool,
+    },
+
+    #[structopt(name = "solve")]
+    /// Mark a problem as solved
+    Solve {
+        /// Problem to mark as solved
+        problem: String,
+        #[structopt(short, long)]
+        /// Mark the problem as unsolved instead
+        undo: bool,
     },
 
+    #[structopt(name = "stats")]
+    /// Show statistics about your progress
+    Stats,
+
     #[structopt(name = "init")]
-    /// Fetch user configuration file
+    /// Add a profile from a kattisrc, named after its host unless --profile
+    /// is given
     Init {
         #[structopt(parse(from_os_str))]
-        /// URL to fetch files from
-        file: PathBuf,
+        /// Path to the kattisrc, looks for ~/.kattisrc if not given
+        file: Option<PathBuf>,
         #[structopt(short, long)]
-        /// Force creation of config file
+        /// Replace an existing profile
         force: bool,
+        #[structopt(long, conflicts_with = "symlink")]
+        /// Copy the kattisrc without asking
+        copy: bool,
+        #[structopt(long)]
+        /// Symlink to the kattisrc without asking
+        symlink: bool,
+        #[structopt(short, long)]
+        /// Create new problems in this language
+        language: Option<Languages>,
+        #[structopt(short, long, parse(from_os_str))]
+        /// Directory to create solutions in
+        code: Option<PathBuf>,
+    },
+
+    #[structopt(name = "login")]
+    /// Log in with your username and password to download your kattisrc and
+    /// add a profile for it
+    Login {
+        #[structopt(long, default_value = "https://open.kattis.com")]
+        /// Kattis host to log in to
+        host: String,
+        #[structopt(short, long)]
+        /// Username or email, asked for if not given
+        username: Option<String>,
+        #[structopt(short, long)]
+        /// Replace an existing profile
+        force: bool,
+    },
+
+    #[structopt(name = "profile")]
+    /// Manage the Kattis profiles
+    Profile {
+        #[structopt(subcommand)]
+        cmd: ProfileCmd,
+    },
+
+    #[structopt(name = "snippet")]
+    /// Use snippets from the snippet library
+    Snippet {
+        #[structopt(subcommand)]
+        cmd: SnippetCmd,
     },
 
     #[structopt(name = "update", alias = "u")]
     Update,
+
+    #[structopt(name = "data")]
+    /// Manage backups of the datafile
+    Data {
+        #[structopt(subcommand)]
+        cmd: DataCmd,
+    },
+}
+
+#[derive(StructOpt, PartialEq, Debug)]
+pub enum ContestCmd {
+    #[structopt(name = "status", alias = "s")]
+    /// Show the time left, your verdicts and the scoreboard of the contest
+    Status {
+        #[structopt(short, long)]
+        /// Keep refreshing the status
+        watch: bool,
+        #[structopt(short, long, default_value = "30")]
+        /// Seconds between refreshes when watching
+        interval: u64,
+    },
+}
+
+#[derive(StructOpt, PartialEq, Debug)]
+pub enum ProfileCmd {
+    #[structopt(name = "list", alias = "l")]
+    /// List the profiles, marking the default one
+    List,
+
+    #[structopt(name = "default", alias = "d")]
+    /// Use a profile when --profile is not given
+    Default {
+        /// Name of the profile
+        name: String,
+    },
+}
+
+#[derive(StructOpt, PartialEq, Debug)]
+pub enum SnippetCmd {
+    #[structopt(name = "add", alias = "a")]
+    /// Insert a snippet into a solution at its `kah: snippets` comment
+    Add {
+        /// Problem to add the snippet to
+        problem: String,
+        /// Name of the snippet
+        snippet: String,
+    },
+
+    #[structopt(name = "list", alias = "l")]
+    /// List the snippets in the library
+    List {
+        #[structopt(short, long)]
+        /// Only list snippets for this language
+        language: Option<Languages>,
+    },
+}
+
+#[derive(StructOpt, PartialEq, Debug)]
+pub enum DataCmd {
+    #[structopt(name = "list", alias = "l")]
+    /// List the available backups of the datafile
+    List,
+
+    #[structopt(name = "restore", alias = "r")]
+    /// Restore the datafile from a backup
+    Restore {
+        /// Name of the backup to restore, prompts for one if not given
+        backup: Option<String>,
+        #[structopt(short, long)]
+        /// Restore the latest backup without prompting
+        latest: bool,
+    },
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -131,57 +406,683 @@ impl TryFrom<u64> for ForceProblemCreation {
 
 fn main() -> Result<()> {
     let opt = Opt::from_args();
+    let options = &KahOptions {
+        offline: opt.offline,
+        profile: opt.profile,
+    };
 
     match opt.cmd {
-        Cmd::Problem { id, force } => create_problem(&id, ForceProblemCreation::try_from(force)?)?,
+        Cmd::Problem { id, force, open } => {
+            create_problem(&id, ForceProblemCreation::try_from(force)?, open, options)?
+        }
+        Cmd::Contest {
+            contest,
+            force,
+            cmd,
+        } => match (contest, cmd) {
+            (_, Some(ContestCmd::Status { watch, interval })) => {
+                contest_status(watch, interval, options)?
+            }
+            (Some(contest), None) => {
+                create_contest(&contest, ForceProblemCreation::try_from(force)?, options)?
+            }
+            (None, None) => return Err(NoContest.into()),
+        },
         Cmd::Test {
             problem_id,
             verbose,
         } => {
-            let kah = Kah::get()?;
-            let problem = match kah.get_problem(&problem_id) {
-                Some(x) => Ok(x),
-                None => Err(NoSuchProblem(problem_id)),
-            }?;
-            let mut test = Test::new(&kah, problem, verbose);
-            test.run()?;
-        }
-        Cmd::Submit { .. } => println!("You are submitting something!"),
-        Cmd::Info { problem } => {
-            let kah = Kah::get()?;
-            let problem = match kah.get_problem(&problem) {
-                Some(problem) => problem,
-                None => return Err(NoSuchProblem(problem).into()),
+            let kah = Kah::get(options)?;
+            let problem = find_problem(&kah, &problem_id)?;
+            kah.touch_problem(&problem.metadata.id)?;
+            let id = problem.metadata.id.clone();
+            let mut test = Test::new(&kah, problem, verbose)?;
+            let result = test.run()?;
+            kah.record_test(&id, &result)?;
+        }
+        Cmd::Submit {
+            problem,
+            language,
+            force,
+        } => {
+            let kah = Kah::get(options)?;
+            let problem = find_problem(&kah, &problem)?;
+            let language = language.unwrap_or_else(|| problem.solution.language.clone());
+            kah.touch_problem(&problem.metadata.id)?;
+
+            let url = submit(&kah, &problem, &language, force)?;
+            println!("Submitted, see {}", url);
+        }
+        Cmd::Bundle { problem, output } => {
+            let kah = Kah::get(options)?;
+            let problem = find_problem(&kah, &problem)?;
+            let file = kah.config.code.join(problem.solution_path());
+            let bundle = bundle(&file, &problem.solution.language)?;
+
+            match output {
+                Some(output) => {
+                    fs::write(&output, bundle.code)?;
+                    println!(
+                        "Bundled {} files into {}",
+                        bundle.files.len(),
+                        output.display()
+                    );
+                }
+                None => print!("{}", bundle.code),
+            }
+        }
+        Cmd::Edit { problem, statement } => {
+            let kah = Kah::get(options)?;
+            let problem = find_problem(&kah, &problem)?;
+            open_problem(&kah, &problem, statement || kah.config.open_statement)?;
+        }
+        Cmd::Open {
+            problem,
+            submission,
+        } => {
+            let kah = Kah::get(options)?;
+            let url = match submission {
+                Some(submission) => {
+                    format!("{}/submissions/{}", kah.get_kattis_url(), submission)
+                }
+                None => {
+                    // structopt makes sure there is a problem, anything that
+                    // is not an id is looked up among the tracked problems
+                    let problem = problem.unwrap_or_default();
+                    let id = match kah.problem_id(&problem)? {
+                        Some(id) => id,
+                        None => find_problem(&kah, &problem)?.metadata.id,
+                    };
+                    format!("{}/problems/{}", kah.get_kattis_url(), id)
+                }
             };
 
-            println!("{}", problem);
+            open_url(&url)?;
         }
-        Cmd::Init { file, force } => {
-            Kah::new(file, force)?;
+        Cmd::Info { problem, statement } => {
+            let kah = Kah::get(options)?;
+            let problem = find_problem(&kah, &problem)?;
+
+            if statement {
+                show_statement(&kah, problem)?;
+            } else {
+                println!("{}", problem);
+            }
+        }
+        Cmd::List {
+            solved,
+            unsolved,
+            language,
+            min_difficulty,
+            max_difficulty,
+            sort,
+            json,
+        } => {
+            let filter = ListFilter {
+                solved: match (solved, unsolved) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                },
+                language,
+                min_difficulty,
+                max_difficulty,
+            };
+            list(filter, sort, json, options)?;
+        }
+        Cmd::Random {
+            min_difficulty,
+            max_difficulty,
+            untracked,
+            seed,
+            daily,
+            refresh,
+            open,
+        } => {
+            let seed = match seed {
+                Some(seed) => seed,
+                None if daily => Local::now().date_naive().to_string(),
+                None => Local::now()
+                    .timestamp_nanos_opt()
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            let filter = RandomFilter {
+                min_difficulty,
+                max_difficulty,
+                ..RandomFilter::default()
+            };
+            random(filter, untracked, &seed, refresh, open, options)?;
+        }
+        Cmd::Solve { problem, undo } => {
+            let kah = Kah::get(options)?;
+            let problem = find_problem(&kah, &problem)?;
+            kah.solve_problem(&problem.metadata.id, !undo)?;
+
+            if undo {
+                println!("Marked {} as unsolved", problem.metadata.name);
+            } else {
+                println!("Marked {} as solved", problem.metadata.name);
+            }
+        }
+        Cmd::Stats => {
+            let kah = Kah::get(options)?;
+            let stats = Stats::new(&kah.problems()?, Local::now().date_naive());
+            print!("{}", stats);
+        }
+        Cmd::Init {
+            file,
+            force,
+            copy,
+            symlink,
+            language,
+            code,
+        } => {
+            let storage = match (copy, symlink) {
+                (true, _) => Some(KattisrcStorage::Copy),
+                (_, true) => Some(KattisrcStorage::Symlink),
+                _ => None,
+            };
+            init(file, force, storage, language, code, options)?;
         }
         Cmd::Update => {
-            let mut kah = Kah::get()?;
+            let mut kah = Kah::get(options)?;
             kah.update()?;
 
             println!("Successfully updated data");
         }
+        Cmd::Login {
+            host,
+            username,
+            force,
+        } => login(&host, username, force, options)?,
+        Cmd::Profile { cmd } => profile(cmd)?,
+        Cmd::Snippet { cmd } => snippet(cmd, options)?,
+        Cmd::Data { cmd } => data(cmd, options)?,
+    }
+
+    Ok(())
+}
+
+fn create_problem(
+    problem_id: &str,
+    force: ForceProblemCreation,
+    open: bool,
+    options: &KahOptions,
+) -> Result<()> {
+    let mut kah = Kah::get(options)?;
+    let language = problem_language(&kah)?;
+    let problem = ProblemMetadata::new(&kah, problem_id)?;
+
+    kah.create_problem(&problem, language, force)?;
+
+    if open || kah.config.open {
+        let problem = find_problem(&kah, &problem.id)?;
+        open_problem(&kah, &problem, kah.config.open_statement)?;
+    }
+
+    Ok(())
+}
+
+/// Opens the solution to a problem in the editor, along with its statement
+/// if asked to.
+fn open_problem(kah: &Kah, problem: &Problem, statement: bool) -> Result<()> {
+    let mut files = vec![kah.config.code.join(problem.solution_path())];
+    let statement_path = kah.config.code.join(problem.statement_path());
+    if statement && statement_path.is_file() {
+        files.push(statement_path);
+    }
+
+    kah.touch_problem(&problem.metadata.id)?;
+    edit(&kah.editor()?, &files)
+}
+
+fn create_contest(contest: &str, force: ForceProblemCreation, options: &KahOptions) -> Result<()> {
+    let mut kah = Kah::get(options)?;
+    let contest = Contest::get(&kah, contest)?;
+    println!(
+        "Found contest {} with {} problems",
+        contest.name,
+        contest.problems.len()
+    );
+
+    let language = problem_language(&kah)?;
+
+    kah.save_contest(&contest)?;
+    let created = contest.create(&mut kah, &language, force)?;
+    println!(
+        "Created {} of {} problems in {}",
+        created,
+        contest.problems.len(),
+        kah.config.code.join(contest.dir()).display()
+    );
+
+    Ok(())
+}
+
+fn contest_status(watch: bool, interval: u64, options: &KahOptions) -> Result<()> {
+    let kah = Kah::get(options)?;
+    kah.contest()?.ok_or(NoContest)?;
+    if !options.offline {
+        if let Err(err) = kah.client.login() {
+            eprintln!("{}", err);
+        }
+    }
+
+    loop {
+        let saved = kah.contest()?.ok_or(NoContest)?;
+        // Refresh the contest for its times, falling back to what we have
+        let contest = match Contest::get(&kah, &saved.url) {
+            Ok(contest) => {
+                kah.save_contest(&contest)?;
+                contest
+            }
+            Err(_) => saved,
+        };
+        let status = ContestStatus::fetch(&kah, contest)?;
+
+        if watch {
+            // Clear the screen and move to the top left
+            print!("\x1b[2J\x1b[H");
+        }
+        print!("{}", status);
+
+        if !watch {
+            return Ok(());
+        }
+        sleep(Duration::from_secs(interval));
+    }
+}
+
+fn list(filter: ListFilter, sort: SortBy, json: bool, options: &KahOptions) -> Result<()> {
+    let kah = Kah::get(options)?;
+    let mut problems: Vec<_> = kah
+        .problems()?
+        .into_iter()
+        .filter(|p| filter.matches(p))
+        .collect();
+    sort_problems(&mut problems, sort);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&problems)?);
+    } else if problems.is_empty() {
+        println!("No problems found");
+    } else {
+        println!("{}", render_table(&problems));
+    }
+
+    Ok(())
+}
+
+/// Picks a problem that is not solved, or not tracked at all, and creates
+/// it unless it is tracked already.
+fn random(
+    mut filter: RandomFilter,
+    untracked: bool,
+    seed: &str,
+    refresh: bool,
+    open: bool,
+    options: &KahOptions,
+) -> Result<()> {
+    let kah = Kah::get(options)?;
+    let tracked = kah.problems()?;
+    filter.exclude = tracked
+        .iter()
+        .filter(|p| untracked || p.solution.solved)
+        .map(|p| p.metadata.id.clone())
+        .collect();
+
+    let problems = kah.problem_list(refresh)?;
+    let problem = choose(&problems, &filter, seed).ok_or(NoRandomProblem)?;
+    match problem.difficulty {
+        Some(difficulty) => println!(
+            "Picked {} ({}), difficulty {}",
+            problem.name, problem.id, difficulty
+        ),
+        None => println!("Picked {} ({})", problem.name, problem.id),
+    }
+
+    match tracked.iter().find(|p| p.metadata.id == problem.id) {
+        Some(tracked) => {
+            println!(
+                "You are already tracking it in {}",
+                kah.config.code.join(tracked.solution_path()).display()
+            );
+            if open || kah.config.open {
+                open_problem(&kah, tracked, kah.config.open_statement)?;
+            }
+            Ok(())
+        }
+        None => create_problem(&problem.id, ForceProblemCreation::Nothing, open, options),
+    }
+}
+
+fn profile(cmd: ProfileCmd) -> Result<()> {
+    let mut file = ConfigFile::load()?.ok_or(NoConfig)?;
+
+    match cmd {
+        ProfileCmd::List => {
+            for (name, profile) in &file.profiles {
+                let marker = if file.default_profile.as_ref() == Some(name) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!(
+                    "{} {: <12} {} ({})",
+                    marker, name, profile.kattis.hostname, profile.kattis.username
+                );
+            }
+        }
+        ProfileCmd::Default { name } => {
+            if !file.profiles.contains_key(&name) {
+                return Err(NoSuchProfile(name).into());
+            }
+            file.default_profile = Some(name.clone());
+            file.save()?;
+
+            println!("Using profile {} by default", name);
+        }
+    }
+
+    Ok(())
+}
+
+fn snippet(cmd: SnippetCmd, options: &KahOptions) -> Result<()> {
+    let kah = Kah::get(options)?;
+
+    match cmd {
+        SnippetCmd::Add { problem, snippet } => {
+            let problem = find_problem(&kah, &problem)?;
+            let path = kah.config.code.join(problem.solution_path());
+            if kah.add_snippet(&path, &problem.solution.language, &snippet)? {
+                kah.touch_problem(&problem.metadata.id)?;
+                println!("Added {} to {}", snippet, path.display());
+            } else {
+                println!("{} already contains {}", path.display(), snippet);
+            }
+        }
+        SnippetCmd::List { language } => {
+            let languages = match language {
+                Some(language) => vec![language],
+                None => Languages::all(),
+            };
+            for language in languages {
+                let snippets = kah.snippets(&language)?;
+                if !snippets.is_empty() {
+                    println!("{}: {}", language, snippets.join(", "));
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
-fn create_problem(problem_id: &str, force: ForceProblemCreation) -> Result<()> {
+fn data(cmd: DataCmd, options: &KahOptions) -> Result<()> {
+    let kah = Kah::get(options)?;
+
+    match cmd {
+        DataCmd::List => {
+            for backup in kah.datafile_backups()? {
+                if let Some(name) = backup.file_name() {
+                    println!("{}", name.to_string_lossy());
+                }
+            }
+        }
+        DataCmd::Restore { backup, latest } => {
+            let backup = match backup {
+                Some(backup) => Some(backup),
+                None if latest => None,
+                None => {
+                    let backups: Vec<_> = kah
+                        .datafile_backups()?
+                        .iter()
+                        .filter_map(|b| b.file_name())
+                        .map(|b| b.to_string_lossy().to_string())
+                        .collect();
+                    if backups.is_empty() {
+                        return Err(NoBackups.into());
+                    }
+
+                    let backup = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt("Select a backup to restore")
+                        .items(&backups[..])
+                        .default(0)
+                        .interact()?;
+                    Some(backups[backup].clone())
+                }
+            };
+
+            let restored = kah.restore_datafile(backup.as_deref())?;
+            println!("Restored datafile from {}", restored.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Looks up a tracked problem, asking which one was meant when several
+/// problems match the query equally well.
+fn find_problem(kah: &Kah, query: &str) -> Result<Problem> {
+    let matches = kah.find_problems(query)?;
+    let best = match matches.first() {
+        Some((score, _)) => *score,
+        None => return Err(NoSuchProblem(query.to_string()).into()),
+    };
+
+    // Only ask when the best matches are equally good
+    let mut candidates: Vec<_> = matches
+        .into_iter()
+        .take_while(|(score, _)| *score == best)
+        .map(|(_, problem)| problem)
+        .collect();
+    if candidates.len() == 1 {
+        return Ok(candidates.remove(0));
+    }
+
+    let items: Vec<_> = candidates
+        .iter()
+        .map(|p| format!("{} ({})", p.metadata.name, p.metadata.id))
+        .collect();
+    let problem = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Several problems match {}, select one", query))
+        .items(&items[..])
+        .default(0)
+        .interact()?;
+
+    Ok(candidates.remove(problem))
+}
+
+fn show_statement(kah: &Kah, problem: Problem) -> Result<()> {
+    let statement = match problem.metadata.statement {
+        Some(statement) => statement,
+        None => {
+            let statement = ProblemMetadata::get(kah, &problem.metadata.id)?.statement;
+            kah.set_statement(&problem.metadata.id, statement.clone())?;
+            statement.ok_or_else(|| NoStatement(problem.metadata.id.clone()))?
+        }
+    };
+
+    let text = format!(
+        "{} ({})\nCPU: {}, MEM: {}, DIF: {}\n\n{}",
+        problem.metadata.name,
+        problem.metadata.id,
+        problem.metadata.cpu_time_limit,
+        problem.metadata.memory_limit,
+        problem.metadata.difficulty,
+        statement::to_text(&statement)
+    );
+    page(&text)
+}
+
+/// Walks through adding a profile: finding and checking the kattisrc, how to
+/// keep it, the defaults for new problems, and whether the credentials work.
+fn init(
+    file: Option<PathBuf>,
+    force: bool,
+    storage: Option<KattisrcStorage>,
+    language: Option<Languages>,
+    code: Option<PathBuf>,
+    options: &KahOptions,
+) -> Result<()> {
+    let kattisrc = match file {
+        Some(file) => file,
+        None => {
+            let file = Kah::find_kattisrc().ok_or(NoKattisrc)?;
+            println!("Found {}", file.display());
+            file
+        }
+    };
+    let kattis = Kah::parse_kattisrc(&kattisrc)?;
+    println!("Using {} on {}", kattis.username, kattis.hostname);
+
+    let storage = match storage {
+        Some(storage) => storage,
+        None => {
+            let choices = &["Copy it", "Symlink to it"];
+            let choice = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("How should kah keep your kattisrc?")
+                .items(&choices[..])
+                .default(0)
+                .interact()?;
+            [KattisrcStorage::Copy, KattisrcStorage::Symlink][choice]
+        }
+    };
+
+    // Only ask for defaults the first time, they are shared by all profiles
+    let existing = ConfigFile::load()?;
+    let (language, code) = match &existing {
+        Some(_) => (language, code),
+        None => {
+            let language = match language {
+                Some(language) => language,
+                None => select_language()?,
+            };
+            let code = match code {
+                Some(code) => code,
+                None => {
+                    let code: String = Input::with_theme(&ColorfulTheme::default())
+                        .with_prompt("Directory for your solutions")
+                        .default(env::current_dir()?.display().to_string())
+                        .interact_text()?;
+                    PathBuf::from(code)
+                }
+            };
+            (Some(language), Some(code))
+        }
+    };
+
+    if !options.offline {
+        let http = existing.map(|f| f.config.http).unwrap_or_default();
+        verify_credentials(&kattis, &http)?;
+    }
+
+    let init = InitOptions {
+        kattisrc,
+        storage,
+        language,
+        code: code
+            .map(|c| env::current_dir().map(|d| d.join(c)))
+            .transpose()?,
+        force,
+    };
+    Kah::new(&init, options)?;
+
+    Ok(())
+}
+
+/// Downloads the kattisrc by logging in with a password and adds a profile
+/// for it like `init` does.
+fn login(host: &str, username: Option<String>, force: bool, options: &KahOptions) -> Result<()> {
+    let username = match username {
+        Some(username) => username,
+        None => Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Username or email")
+            .interact_text()?,
+    };
+    let password = Password::with_theme(&ColorfulTheme::default())
+        .with_prompt("Password")
+        .interact()?;
+
+    let host = if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("https://{}", host)
+    };
+    let kattis = Kattis {
+        username: username.clone(),
+        token: Secret::default(),
+        submit: format!("{}/submit", host),
+        hostname: host,
+    };
+    let http = ConfigFile::load()?
+        .map(|f| f.config.http)
+        .unwrap_or_default();
+
+    let dir = tempfile::tempdir()?;
+    let client = KattisClient::new(&kattis, &http, dir.path(), options.offline)?;
+    client.login_with_password(&username, &password)?;
+    let kattisrc = dir.path().join(".kattisrc");
+    fs::write(&kattisrc, client.download_kattisrc()?)?;
+    println!("Downloaded the kattisrc of {}", username);
+
+    init(
+        Some(kattisrc),
+        force,
+        Some(KattisrcStorage::Copy),
+        None,
+        None,
+        options,
+    )
+}
+
+/// Logs in with a throwaway client, asking whether to carry on if it fails.
+fn verify_credentials(kattis: &Kattis, http: &ClientConfig) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let client = KattisClient::new(kattis, http, dir.path(), false)?;
+
+    match client.login() {
+        Ok(()) => {
+            println!("Logged in as {}", kattis.username);
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            let keep = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Add the profile anyway?")
+                .default(false)
+                .interact()?;
+
+            if keep {
+                Ok(())
+            } else {
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Problems are created in the language of the workspace, the configured
+/// language, or one picked when neither is set.
+fn problem_language(kah: &Kah) -> Result<Languages> {
+    let language = kah
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.language.clone());
+
+    match language.or_else(|| kah.config.language.clone()) {
+        Some(language) => Ok(language),
+        None => select_language(),
+    }
+}
+
+fn select_language() -> Result<Languages> {
     let languages = &["Rust", "Kotlin", "Java", "Python", "Haskell"];
     let language = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select a language to solve problem in")
         .items(&languages[..])
         .interact()?;
 
-    let language = Languages::from_str(languages[language])?;
-    let problem = ProblemMetadata::new(problem_id)?;
-    let mut kah = Kah::get()?;
-
-    kah.create_problem(&problem, language, force)?;
-
-    Ok(())
+    Ok(Languages::from_str(languages[language])?)
 }
@@ -1,15 +1,12 @@
-use crate::{
-    error::KahError::{FetchError, ScrapeError},
-    kah::Kah,
-    utils::*,
-};
+use crate::{error::KahError::ScrapeError, kah::Kah, statement, utils::*};
 use anyhow::Result;
 use select::{
     document::Document,
-    predicate::{Class, Name, Predicate},
+    node::Node,
+    predicate::{Class, Element, Name, Predicate},
 };
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Write};
+use std::{collections::HashMap, fs::File, io::Write};
 use tempfile::tempdir;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -35,92 +32,110 @@ pub struct ProblemMetadata {
     pub(crate) memory_limit: String,
     pub(crate) difficulty: f32,
     pub(crate) samples: Vec<Sample>,
+    /// HTML of the problem statement
+    #[serde(default)]
+    pub(crate) statement: Option<String>,
 }
 
 impl ProblemMetadata {
-    pub(crate) fn new(id: &str) -> Result<ProblemMetadata> {
-        let mut problem = ProblemMetadata::get(id)?;
+    pub(crate) fn new(kah: &Kah, id: &str) -> Result<ProblemMetadata> {
+        let mut problem = ProblemMetadata::get(kah, id)?;
 
         println!("Found problem {}, fetching data", problem.name);
-        problem.get_samples()?;
+        problem.get_samples(kah)?;
 
         Ok(problem)
     }
 
-    pub fn get(id: &str) -> Result<ProblemMetadata> {
-        let url = Kah::get()?.get_kattis_url();
-        let path: String = format!("{}/problems/{}", url, id);
-        let resp = reqwest::blocking::get(&path)?;
+    pub(crate) fn get(kah: &Kah, id: &str) -> Result<ProblemMetadata> {
+        let body = kah.client.get_text(&format!("/problems/{}", id))?;
+
+        ProblemMetadata::parse(id, &body)
+    }
 
-        let body = match resp.error_for_status() {
-            Ok(resp) => resp.text()?,
-            Err(err) => return Err(FetchError(id.to_string(), err.to_string()).into()),
-        };
+    /// Scrapes the metadata from a problem page. Fields are located by their
+    /// labels rather than their position, so it works for both old and new
+    /// Kattis layouts.
+    pub(crate) fn parse(id: &str, body: &str) -> Result<ProblemMetadata> {
+        let document = Document::from(body);
+        let scrape_error = |msg: &str| ScrapeError(id.to_string(), msg.to_string());
 
-        let document = Document::from(&body[..]);
-        let title = document
+        let name = document
             .find(Class("headline-wrapper").descendant(Name("h1")))
-            .next()
-            .ok_or_else(|| ScrapeError(id.to_string(), "Could not find title".to_string()))?
-            .text();
+            .chain(document.find(Name("h1")))
+            .map(|n| collapse_whitespace(&n.text()))
+            .find(|n| !n.is_empty())
+            .ok_or_else(|| scrape_error("Could not find title"))?;
 
-        let name = title;
+        let cpu_time_limit = find_labelled(&document, &["CPU Time limit", "Time limit"])
+            .ok_or_else(|| scrape_error("Could not find CPU time limit"))?;
+        let memory_limit = find_labelled(&document, &["Memory limit"])
+            .ok_or_else(|| scrape_error("Could not find memory limit"))?;
+        let difficulty = find_labelled(&document, &["Difficulty"])
+            .ok_or_else(|| scrape_error("Could not find difficulty"))?;
+        let difficulty = parse_difficulty(&difficulty)
+            .ok_or_else(|| scrape_error(&format!("Could not parse difficulty {}", difficulty)))?;
 
-        let sidebar = document
-            .find(Class("problem-download"))
+        let statement = document
+            .find(Class("problembody"))
             .next()
-            .ok_or_else(|| ScrapeError(id.to_string(), "Could not find problem download".into()))?
-            .parent()
-            .ok_or_else(|| ScrapeError(id.to_string(), "Could not find sidebar".to_string()))?
-            .find(Name("p"))
-            .collect::<Vec<_>>();
-
-        let cpu_time_limit = sidebar[1]
-            .children()
-            .nth(1)
-            .ok_or_else(|| ScrapeError(id.to_string(), "Could not find CPU time limit".into()))?
-            .text();
-        let memory_limit = sidebar[2]
-            .children()
-            .nth(1)
-            .ok_or_else(|| ScrapeError(id.to_string(), "Could not find memory limit".to_string()))?
-            .text();
-        let difficulty: f32 = sidebar[3]
-            .find(Name("span"))
-            .next()
-            .ok_or_else(|| ScrapeError(id.to_string(), "Could not find difficulty".to_string()))?
-            .text()
-            .parse()?;
+            .map(|n| n.inner_html());
 
         Ok(ProblemMetadata {
             id: id.into(),
-            name: name.trim().to_string(),
-            cpu_time_limit: cpu_time_limit.trim().to_string(),
-            memory_limit: memory_limit.trim().to_string(),
+            name,
+            cpu_time_limit,
+            memory_limit,
             difficulty,
             samples: Vec::new(),
+            statement,
         })
     }
 
-    fn get_samples(&mut self) -> Result<()> {
+    fn get_samples(&mut self, kah: &Kah) -> Result<()> {
         let temp_dir = tempdir()?;
         let file_path = temp_dir.path().join("samples.zip");
 
         let mut temp_file = File::create(&file_path)?;
 
-        let url = Kah::get()?.get_kattis_url();
-
-        let path: String = self.sample_files_url(url);
-        let response = reqwest::blocking::get(&path)?;
+        let response = kah.client.get(&self.sample_files_path())?;
 
-        temp_file.write_all(&response.bytes()?)?;
+        temp_file.write_all(&response)?;
         self.samples = unzip(&file_path)?;
         temp_dir.close()?;
         Ok(())
     }
 
-    fn sample_files_url(&self, url: String) -> String {
-        format!("{}/problems/{}/file/statement/samples.zip", url, self.id)
+    fn sample_files_path(&self) -> String {
+        format!("/problems/{}/file/statement/samples.zip", self.id)
+    }
+
+    /// Renders the problem as Markdown, with its limits, a link to it and the
+    /// samples after the statement.
+    pub(crate) fn to_markdown(&self, url: &str, images: &HashMap<String, String>) -> String {
+        let mut markdown = format!("# {}\n\n", self.name);
+        markdown.push_str(&format!("- Problem ID: {}\n", self.id));
+        markdown.push_str(&format!("- CPU time limit: {}\n", self.cpu_time_limit));
+        markdown.push_str(&format!("- Memory limit: {}\n", self.memory_limit));
+        markdown.push_str(&format!("- Difficulty: {}\n", self.difficulty));
+        markdown.push_str(&format!("- Source: <{}>\n", url));
+
+        if let Some(statement) = &self.statement {
+            markdown.push('\n');
+            markdown.push_str(&statement::to_markdown(statement, images));
+            markdown.push('\n');
+        }
+
+        for (i, sample) in self.samples.iter().enumerate() {
+            markdown.push_str(&format!(
+                "\n## Sample {}\n\n### Input\n\n```\n{}\n```\n\n### Output\n\n```\n{}\n```\n",
+                i + 1,
+                sample.input.trim_end(),
+                sample.expected.trim_end()
+            ));
+        }
+
+        markdown
     }
 
     pub(crate) fn as_os_str(&self) -> String {
@@ -130,3 +145,185 @@ impl ProblemMetadata {
             .collect()
     }
 }
+
+/// Finds the value of the first field labelled with one of `labels`, e.g.
+/// `<p><strong>Memory limit:</strong> 1024 MB</p>` or
+/// `<div><span>Memory limit</span><span>1024 MB</span></div>`.
+pub(crate) fn find_labelled(document: &Document, labels: &[&str]) -> Option<String> {
+    labels.iter().find_map(|label| {
+        document
+            .find(Element)
+            .filter(|node| is_label(node, label))
+            // Use the outermost element that only contains the label
+            .find(|node| node.parent().is_none_or(|p| !is_label(&p, label)))
+            .and_then(|node| labelled_value(&node))
+    })
+}
+
+fn is_label(node: &Node, label: &str) -> bool {
+    let text = collapse_whitespace(&node.text());
+    text.trim_end_matches(':')
+        .trim()
+        .eq_ignore_ascii_case(label)
+}
+
+fn labelled_value(label: &Node) -> Option<String> {
+    let mut siblings = std::iter::successors(label.next(), |n| n.next());
+
+    let value = match label.name() {
+        // Definition lists and tables keep the value in the next element
+        Some("dt") | Some("th") => siblings.find(|n| n.name().is_some())?.text(),
+        _ => siblings.map(|n| n.text()).collect(),
+    };
+
+    let value = collapse_whitespace(&value);
+    let value = value.trim_start_matches(':').trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Parses difficulties like `2.3`, `2.3 Easy`, ranges like `2.1 - 3.4`, which
+/// becomes the middle of the range, and textual ones like `Medium`.
+pub(crate) fn parse_difficulty(text: &str) -> Option<f32> {
+    let numbers: Vec<f32> = text
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter_map(|n| n.parse().ok())
+        .collect();
+
+    match numbers[..] {
+        [difficulty] => Some(difficulty),
+        [low, high] => Some((low + high) / 2.0),
+        [] => {
+            let text = text.to_lowercase();
+            if text.contains("easy") {
+                Some(EASY_DIFFICULTY)
+            } else if text.contains("medium") {
+                Some(MEDIUM_DIFFICULTY)
+            } else if text.contains("hard") {
+                Some(HARD_DIFFICULTY)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+const EASY_DIFFICULTY: f32 = 2.0;
+const MEDIUM_DIFFICULTY: f32 = 4.0;
+const HARD_DIFFICULTY: f32 = 7.0;
+
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_LAYOUT: &str = include_str!("../tests/fixtures/old_layout.html");
+    const NEW_LAYOUT: &str = include_str!("../tests/fixtures/new_layout.html");
+    const RANGE_DIFFICULTY: &str = include_str!("../tests/fixtures/range_difficulty.html");
+    const TEXT_DIFFICULTY: &str = include_str!("../tests/fixtures/text_difficulty.html");
+    const DEFINITION_LIST: &str = include_str!("../tests/fixtures/definition_list.html");
+    const MISSING_DIFFICULTY: &str = include_str!("../tests/fixtures/missing_difficulty.html");
+
+    #[test]
+    fn parses_old_layout() {
+        let problem = ProblemMetadata::parse("hello", OLD_LAYOUT).unwrap();
+
+        assert_eq!(problem.id, "hello");
+        assert_eq!(problem.name, "Hello World!");
+        assert_eq!(problem.cpu_time_limit, "1 second");
+        assert_eq!(problem.memory_limit, "1024 MB");
+        assert_eq!(problem.difficulty, 1.2);
+        assert!(problem.statement.unwrap().contains("There is no input"));
+    }
+
+    #[test]
+    fn parses_new_layout() {
+        let problem = ProblemMetadata::parse("twostones", NEW_LAYOUT).unwrap();
+
+        assert_eq!(problem.name, "Take Two Stones");
+        assert_eq!(problem.cpu_time_limit, "1 second");
+        assert_eq!(problem.memory_limit, "1024 MB");
+        assert_eq!(problem.difficulty, 1.3);
+        assert!(problem.statement.unwrap().contains("single integer"));
+    }
+
+    #[test]
+    fn parses_difficulty_ranges() {
+        let problem = ProblemMetadata::parse("greedy", RANGE_DIFFICULTY).unwrap();
+
+        assert_eq!(problem.name, "Greedy Polygons");
+        assert_eq!(problem.cpu_time_limit, "2 seconds");
+        assert_eq!(problem.memory_limit, "2048 MB");
+        assert!((problem.difficulty - 2.75).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn parses_textual_difficulty() {
+        let problem = ProblemMetadata::parse("dance", TEXT_DIFFICULTY).unwrap();
+
+        assert_eq!(problem.name, "Dance Reconstruction");
+        assert_eq!(problem.difficulty, HARD_DIFFICULTY);
+    }
+
+    #[test]
+    fn parses_definition_lists() {
+        let problem = ProblemMetadata::parse("carrots", DEFINITION_LIST).unwrap();
+
+        assert_eq!(problem.name, "Solving for Carrots");
+        assert_eq!(problem.cpu_time_limit, "1 second");
+        assert_eq!(problem.memory_limit, "1024 MB");
+        assert_eq!(problem.difficulty, 1.1);
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        let error = ProblemMetadata::parse("broken", MISSING_DIFFICULTY).unwrap_err();
+
+        assert_eq!(
+            error.downcast::<crate::error::KahError>().unwrap(),
+            ScrapeError("broken".into(), "Could not find difficulty".into())
+        );
+    }
+
+    #[test]
+    fn parses_difficulties() {
+        assert_eq!(parse_difficulty("3.4"), Some(3.4));
+        assert_eq!(parse_difficulty("1.4 Easy"), Some(1.4));
+        assert_eq!(parse_difficulty("2.0 - 4.0"), Some(3.0));
+        assert_eq!(parse_difficulty("2.0–4.0 Medium"), Some(3.0));
+        assert_eq!(parse_difficulty("Easy"), Some(EASY_DIFFICULTY));
+        assert_eq!(parse_difficulty("Medium"), Some(MEDIUM_DIFFICULTY));
+        assert_eq!(parse_difficulty("unknown"), None);
+    }
+
+    #[test]
+    fn renders_markdown() {
+        let mut problem = ProblemMetadata::parse("hello", OLD_LAYOUT).unwrap();
+        problem.statement = Some(include_str!("../tests/fixtures/statement.html").to_string());
+        problem.samples = vec![Sample {
+            input: String::new(),
+            expected: "Hello World!\n".to_string(),
+        }];
+        // An image that could not be downloaded keeps its URL on Kattis
+        let src = "/problems/sum/file/statement/en/img-0001.png";
+        let images = HashMap::from([(src.to_string(), format!("https://open.kattis.com{}", src))]);
+
+        let markdown = problem.to_markdown("https://open.kattis.com/problems/hello", &images);
+        assert!(markdown.starts_with(
+            "# Hello World!\n\n- Problem ID: hello\n- CPU time limit: 1 second\n\
+             - Memory limit: 1024 MB\n- Difficulty: 1.2\n\
+             - Source: <https://open.kattis.com/problems/hello>\n\n## Input\n"
+        ));
+        assert!(markdown.contains(&format!("![A sum](https://open.kattis.com{})", src)));
+        assert!(markdown.ends_with(
+            "## Sample 1\n\n### Input\n\n```\n\n```\n\n### Output\n\n```\nHello World!\n```\n"
+        ));
+    }
+}
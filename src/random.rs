@@ -0,0 +1,238 @@
+use crate::{
+    contest::problem_id,
+    kah::Kah,
+    problem::{collapse_whitespace, parse_difficulty},
+    utils::write_atomically,
+};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use select::{document::Document, node::Node, predicate::Name};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs::read_to_string, path::PathBuf};
+
+/// Days before the cached problem list is fetched again.
+const PROBLEM_LIST_DAYS: i64 = 7;
+
+/// Kattis has a few thousand problems at a hundred per page, this only stops
+/// us from fetching forever when the pages never run out.
+const MAX_PAGES: usize = 200;
+
+/// A problem in the problem list of Kattis, new problems have no difficulty
+/// yet.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct ListedProblem {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) difficulty: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProblemList {
+    fetched: DateTime<Utc>,
+    problems: Vec<ListedProblem>,
+}
+
+impl Kah {
+    fn problem_list_file(&self) -> PathBuf {
+        self.datafile_dir().join("problems.json")
+    }
+
+    /// Every problem on Kattis, cached for a week unless `refresh` is set.
+    /// Falls back to the cached list when Kattis cannot be reached.
+    pub(crate) fn problem_list(&self, refresh: bool) -> Result<Vec<ListedProblem>> {
+        let cached = read_to_string(self.problem_list_file())
+            .ok()
+            .and_then(|json| serde_json::from_str::<ProblemList>(&json).ok());
+        if let Some(list) = &cached {
+            if !refresh && Utc::now() - list.fetched < Duration::days(PROBLEM_LIST_DAYS) {
+                return Ok(list.problems.clone());
+            }
+        }
+
+        match self.fetch_problem_list() {
+            Ok(problems) => {
+                let list = ProblemList {
+                    fetched: Utc::now(),
+                    problems,
+                };
+                let json = serde_json::to_string(&list)?;
+                write_atomically(&self.problem_list_file(), json.as_bytes())?;
+                Ok(list.problems)
+            }
+            Err(err) => match cached {
+                Some(list) => {
+                    eprintln!("{}, using the problem list from {}", err, list.fetched);
+                    Ok(list.problems)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Fetches the pages of the problem list until one has no new problems.
+    fn fetch_problem_list(&self) -> Result<Vec<ListedProblem>> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+
+        for page in 0..MAX_PAGES {
+            let body = self.client.get_text(&format!("/problems?page={}", page))?;
+            let new: Vec<_> = parse_problem_list(&body)
+                .into_iter()
+                .filter(|p| seen.insert(p.id.clone()))
+                .collect();
+            if new.is_empty() {
+                break;
+            }
+            problems.extend(new);
+        }
+
+        Ok(problems)
+    }
+}
+
+/// Parses a page of the problem list, a table with a row per problem where
+/// the difficulty is in an element with a `difficulty` class.
+fn parse_problem_list(body: &str) -> Vec<ListedProblem> {
+    let document = Document::from(body);
+    let mut seen = HashSet::new();
+
+    document
+        .find(Name("tr"))
+        .filter_map(|row| {
+            let (link, id) = row
+                .find(Name("a"))
+                .find_map(|a| Some((a, problem_id(a.attr("href")?)?)))?;
+            let difficulty = row
+                .find(|n: &Node| n.attr("class").is_some_and(|c| c.contains("difficulty")))
+                .find_map(|n| parse_difficulty(&n.text()));
+
+            Some(ListedProblem {
+                id,
+                name: collapse_whitespace(&link.text()),
+                difficulty,
+            })
+        })
+        .filter(|p| seen.insert(p.id.clone()))
+        .collect()
+}
+
+/// Which problems may be picked.
+#[derive(Debug, Default)]
+pub(crate) struct RandomFilter {
+    pub(crate) min_difficulty: Option<f32>,
+    pub(crate) max_difficulty: Option<f32>,
+    /// IDs of problems to never pick, e.g. the solved ones
+    pub(crate) exclude: HashSet<String>,
+}
+
+impl RandomFilter {
+    /// Problems without a difficulty only match when there is no range.
+    fn matches(&self, problem: &ListedProblem) -> bool {
+        let in_range = match problem.difficulty {
+            Some(difficulty) => {
+                self.min_difficulty.is_none_or(|min| difficulty >= min)
+                    && self.max_difficulty.is_none_or(|max| difficulty <= max)
+            }
+            None => self.min_difficulty.is_none() && self.max_difficulty.is_none(),
+        };
+
+        in_range && !self.exclude.contains(&problem.id)
+    }
+}
+
+/// Picks the matching problem with the lowest hash of the seed and its id.
+/// The same seed gives everyone the same problem, and someone who solved it
+/// gets the one that comes next for that seed, regardless of the order of
+/// the list or problems being added to Kattis.
+pub(crate) fn choose<'a>(
+    problems: &'a [ListedProblem],
+    filter: &RandomFilter,
+    seed: &str,
+) -> Option<&'a ListedProblem> {
+    problems
+        .iter()
+        .filter(|p| filter.matches(p))
+        .min_by_key(|p| hash(seed, &p.id))
+}
+
+/// FNV-1a, which unlike the hasher of the standard library is the same on
+/// every machine and version.
+fn hash(seed: &str, id: &str) -> u64 {
+    let bytes = seed.bytes().chain(Some(0)).chain(id.bytes());
+
+    bytes.fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBLEM_LIST: &str = include_str!("../tests/fixtures/problem_list.html");
+
+    #[test]
+    fn parses_problem_lists() {
+        let problems = parse_problem_list(PROBLEM_LIST);
+
+        assert_eq!(
+            problems,
+            vec![
+                ListedProblem {
+                    id: "hello".to_string(),
+                    name: "Hello World!".to_string(),
+                    difficulty: Some(1.2),
+                },
+                ListedProblem {
+                    id: "different".to_string(),
+                    name: "A Different Problem".to_string(),
+                    difficulty: Some(2.6),
+                },
+                ListedProblem {
+                    id: "newproblem".to_string(),
+                    name: "Brand New".to_string(),
+                    difficulty: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn chooses_by_seed() {
+        let problems: Vec<_> = (0..50)
+            .map(|i| ListedProblem {
+                id: format!("problem{}", i),
+                name: format!("Problem {}", i),
+                difficulty: Some(1.0 + i as f32 / 10.0),
+            })
+            .collect();
+        let filter = RandomFilter {
+            min_difficulty: Some(2.0),
+            max_difficulty: Some(3.0),
+            ..RandomFilter::default()
+        };
+
+        let chosen = choose(&problems, &filter, "2026-10-19").unwrap();
+        let difficulty = chosen.difficulty.unwrap();
+        assert!((2.0..=3.0).contains(&difficulty));
+
+        let mut reversed = problems.clone();
+        reversed.reverse();
+        assert_eq!(choose(&reversed, &filter, "2026-10-19"), Some(chosen));
+
+        // Solving the problem of the day moves on to another one
+        let solved = RandomFilter {
+            exclude: HashSet::from([chosen.id.clone()]),
+            ..filter
+        };
+        let next = choose(&problems, &solved, "2026-10-19").unwrap();
+        assert_ne!(next, chosen);
+
+        let untested = ListedProblem {
+            difficulty: None,
+            ..problems[0].clone()
+        };
+        assert!(!solved.matches(&untested));
+        assert!(RandomFilter::default().matches(&untested));
+    }
+}
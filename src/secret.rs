@@ -0,0 +1,372 @@
+use crate::{
+    error::KahError::{SecretCommandError, WrongPassphrase},
+    utils::write_atomically,
+};
+use aes_gcm::{
+    aead::{generic_array::GenericArray, AeadInPlace, KeyInit},
+    Aes256Gcm,
+};
+use anyhow::{anyhow, Result};
+use dialoguer::{theme::ColorfulTheme, Password};
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Sha256;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    env,
+    fmt::{self, Formatter},
+    fs::read_to_string,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+const PASSPHRASE_VAR: &str = "KAH_PASSPHRASE";
+const ITERATIONS: u32 = 200_000;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// A value like the Kattis token that must never end up in logs, `Debug`
+/// output or error messages.
+#[derive(Clone, Default, PartialEq)]
+pub(crate) struct Secret(String);
+
+impl Secret {
+    pub(crate) fn new(secret: String) -> Self {
+        Secret(secret)
+    }
+
+    pub(crate) fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Secret([redacted])")
+    }
+}
+
+/// Secrets are only read from older configuration files and never written
+/// back, serializing one gives an empty string.
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Secret(String::deserialize(deserializer)?))
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("")
+    }
+}
+
+/// Where secrets are kept, chosen in the configuration.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub(crate) enum SecretsConfig {
+    /// A file encrypted with a passphrase
+    #[default]
+    File,
+    /// External commands, e.g. `pass` or `secret-tool`, where `{key}` is
+    /// replaced by the name of the secret. `get` prints the secret and `set`
+    /// reads it from stdin.
+    Command { get: String, set: String },
+}
+
+pub(crate) trait SecretStore: fmt::Debug {
+    fn get(&self, key: &str) -> Result<Option<Secret>>;
+    fn set(&self, key: &str, secret: &Secret) -> Result<()>;
+}
+
+/// Creates the secret store the configuration asks for.
+pub(crate) fn store(config: &SecretsConfig, dir: &Path) -> Box<dyn SecretStore> {
+    match config {
+        SecretsConfig::File => Box::new(EncryptedFile::new(&dir.join("secrets.json"))),
+        SecretsConfig::Command { get, set } => Box::new(CommandStore {
+            get: get.clone(),
+            set: set.clone(),
+        }),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct EncryptedEntries {
+    salt: String,
+    entries: BTreeMap<String, EncryptedEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedEntry {
+    nonce: String,
+    data: String,
+    tag: String,
+}
+
+/// Secrets encrypted with AES-256-GCM, using a key derived from a passphrase
+/// that is taken from `KAH_PASSPHRASE` or asked for.
+#[derive(Debug)]
+pub(crate) struct EncryptedFile {
+    path: PathBuf,
+    passphrase: RefCell<Option<Secret>>,
+}
+
+impl EncryptedFile {
+    pub(crate) fn new(path: &Path) -> Self {
+        EncryptedFile {
+            path: path.to_owned(),
+            passphrase: RefCell::new(None),
+        }
+    }
+
+    #[cfg(test)]
+    fn with_passphrase(path: &Path, passphrase: &str) -> Self {
+        EncryptedFile {
+            path: path.to_owned(),
+            passphrase: RefCell::new(Some(Secret::new(passphrase.to_string()))),
+        }
+    }
+
+    fn passphrase(&self, new: bool) -> Result<Secret> {
+        if let Some(passphrase) = &*self.passphrase.borrow() {
+            return Ok(passphrase.clone());
+        }
+
+        let passphrase = match env::var(PASSPHRASE_VAR) {
+            Ok(passphrase) => passphrase,
+            Err(_) if new => Password::with_theme(&ColorfulTheme::default())
+                .with_prompt("Choose a passphrase for your Kattis credentials")
+                .with_confirmation("Repeat the passphrase", "The passphrases do not match")
+                .interact()?,
+            Err(_) => Password::with_theme(&ColorfulTheme::default())
+                .with_prompt("Passphrase for your Kattis credentials")
+                .interact()?,
+        };
+        let passphrase = Secret::new(passphrase);
+        *self.passphrase.borrow_mut() = Some(passphrase.clone());
+
+        Ok(passphrase)
+    }
+
+    fn read(&self) -> Result<Option<EncryptedEntries>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_str(&read_to_string(&self.path)?)?))
+    }
+
+    fn key(&self, salt: &[u8], new: bool) -> Result<[u8; KEY_LEN]> {
+        let passphrase = self.passphrase(new)?;
+        let mut key = [0; KEY_LEN];
+        pbkdf2_hmac::<Sha256>(passphrase.expose().as_bytes(), salt, ITERATIONS, &mut key);
+
+        Ok(key)
+    }
+}
+
+impl SecretStore for EncryptedFile {
+    fn get(&self, key: &str) -> Result<Option<Secret>> {
+        let file = match self.read()? {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let entry = match file.entries.get(key) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let encryption_key = self.key(&from_hex(&file.salt)?, false)?;
+        decrypt(&encryption_key, key, entry).map(Some)
+    }
+
+    fn set(&self, key: &str, secret: &Secret) -> Result<()> {
+        let mut file = match self.read()? {
+            Some(file) => file,
+            None => {
+                let salt = random_bytes::<SALT_LEN>()?;
+                EncryptedEntries {
+                    salt: to_hex(&salt),
+                    entries: BTreeMap::new(),
+                }
+            }
+        };
+
+        let new = file.entries.is_empty();
+        let encryption_key = self.key(&from_hex(&file.salt)?, new)?;
+        // Make sure every secret in the file uses the same passphrase
+        if let Some((name, entry)) = file.entries.iter().next() {
+            decrypt(&encryption_key, name, entry)?;
+        }
+
+        let nonce = random_bytes::<NONCE_LEN>()?;
+        let mut data = secret.expose().as_bytes().to_vec();
+        let tag = Aes256Gcm::new(&encryption_key.into())
+            .encrypt_in_place_detached(&nonce.into(), key.as_bytes(), &mut data)
+            .map_err(|_| anyhow!("Could not encrypt {}", key))?;
+
+        file.entries.insert(
+            key.to_string(),
+            EncryptedEntry {
+                nonce: to_hex(&nonce),
+                data: to_hex(&data),
+                tag: to_hex(&tag),
+            },
+        );
+
+        let json = serde_json::to_string_pretty(&file)?;
+        write_atomically(&self.path, json.as_bytes())
+    }
+}
+
+/// Decrypts a secret, its name is authenticated along with it so entries
+/// cannot be swapped around.
+fn decrypt(key: &[u8; KEY_LEN], name: &str, entry: &EncryptedEntry) -> Result<Secret> {
+    let nonce = from_hex(&entry.nonce)?;
+    let tag = from_hex(&entry.tag)?;
+    if nonce.len() != NONCE_LEN || tag.len() != TAG_LEN {
+        return Err(WrongPassphrase.into());
+    }
+
+    let mut secret = from_hex(&entry.data)?;
+    Aes256Gcm::new(key.into())
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(&nonce),
+            name.as_bytes(),
+            &mut secret,
+            GenericArray::from_slice(&tag),
+        )
+        .map_err(|_| WrongPassphrase)?;
+
+    Ok(Secret::new(String::from_utf8(secret)?))
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("Could not get random bytes: {}", e))?;
+
+    Ok(bytes)
+}
+
+/// Secrets kept by external commands, so any password manager or keyring
+/// with a command line interface can be used.
+#[derive(Debug)]
+pub(crate) struct CommandStore {
+    get: String,
+    set: String,
+}
+
+impl CommandStore {
+    /// Runs a command through the shell, `cmd` on Windows.
+    fn command(template: &str, key: &str) -> Command {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut command = Command::new(shell);
+        command.arg(flag).arg(template.replace("{key}", key));
+        command
+    }
+}
+
+impl SecretStore for CommandStore {
+    fn get(&self, key: &str) -> Result<Option<Secret>> {
+        let output = CommandStore::command(&self.get, key)
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let secret = String::from_utf8(output.stdout)?.trim_end().to_string();
+        Ok(Some(Secret::new(secret)).filter(|s| !s.is_empty()))
+    }
+
+    fn set(&self, key: &str, secret: &Secret) -> Result<()> {
+        let mut child = CommandStore::command(&self.set, key)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        if let Some(stdin) = child.stdin.as_mut() {
+            writeln!(stdin, "{}", secret.expose())?;
+        }
+
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(SecretCommandError(self.set.clone(), status.to_string()).into())
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(text.get(i..i + 2).unwrap_or("?"), 16)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_secrets_with_a_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.json");
+        let secret = Secret::new("hunter2".to_string());
+
+        EncryptedFile::with_passphrase(&path, "correct horse")
+            .set("open.token", &secret)
+            .unwrap();
+
+        assert!(!read_to_string(&path).unwrap().contains("hunter2"));
+        let store = EncryptedFile::with_passphrase(&path, "correct horse");
+        assert_eq!(store.get("open.token").unwrap(), Some(secret));
+        assert_eq!(store.get("kth.token").unwrap(), None);
+
+        let store = EncryptedFile::with_passphrase(&path, "wrong");
+        assert!(store.get("open.token").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn stores_secrets_with_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("{key}").display().to_string();
+        let store = CommandStore {
+            // A missing secret makes `cat` fail, which is expected here
+            get: format!("cat '{}' 2>/dev/null", file),
+            set: format!("cat > '{}'", file),
+        };
+
+        assert_eq!(store.get("token").unwrap(), None);
+        store
+            .set("token", &Secret::new("hunter2".to_string()))
+            .unwrap();
+        assert_eq!(
+            store.get("token").unwrap().as_ref().map(Secret::expose),
+            Some("hunter2")
+        );
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        let secret = Secret::new("hunter2".to_string());
+
+        assert!(!format!("{:?}", secret).contains("hunter2"));
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"\"");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    error::KahError::{NoSnippetMarker, NoSuchSnippet},
+    kah::Kah,
+    languages::Languages,
+};
+use anyhow::Result;
+use std::{
+    fs,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// Comment text marking where snippets are inserted, e.g. `// kah: snippets`.
+const MARKER: &str = "kah: snippets";
+
+impl Kah {
+    /// Directory of the snippet library, with a folder per language, e.g.
+    /// `snippets/rust/union_find.rs`.
+    pub(crate) fn snippet_dir(&self) -> PathBuf {
+        match &self.config.snippets {
+            Some(dir) => dir.clone(),
+            None => self.config.dir.join("snippets"),
+        }
+    }
+
+    /// Names of the snippets in the library for a language.
+    pub(crate) fn snippets(&self, language: &Languages) -> Result<Vec<String>> {
+        let dir = self.snippet_dir().join(language.language_path());
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut snippets: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension() == Some(language.extension().as_ref()))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+            .collect();
+        snippets.sort_unstable();
+
+        Ok(snippets)
+    }
+
+    pub(crate) fn snippet(&self, language: &Languages, name: &str) -> Result<String> {
+        let path = self
+            .snippet_dir()
+            .join(language.language_path())
+            .join(format!("{}.{}", name, language.extension()));
+
+        if path.is_file() {
+            Ok(read_to_string(path)?)
+        } else {
+            Err(NoSuchSnippet(name.to_string(), language.to_string()).into())
+        }
+    }
+
+    /// Inserts the snippets configured for a language into a new solution,
+    /// warning instead of failing when they cannot be.
+    pub(crate) fn insert_default_snippets(&self, language: &Languages, code: String) -> String {
+        let names = match self.config.default_snippets.get(language) {
+            Some(names) => names,
+            None => return code,
+        };
+
+        names.iter().fold(code, |code, name| {
+            let snippet = match self.snippet(language, name) {
+                Ok(snippet) => snippet,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    return code;
+                }
+            };
+
+            match insert_snippet(&code, language, &snippet) {
+                Some(inserted) => inserted,
+                None => {
+                    eprintln!(
+                        "The template has no `{}` comment, skipping snippet {}",
+                        marker(language),
+                        name
+                    );
+                    code
+                }
+            }
+        })
+    }
+
+    /// Adds a snippet to the solution file at `path`, returns whether it was
+    /// not there already.
+    pub(crate) fn add_snippet(
+        &self,
+        path: &Path,
+        language: &Languages,
+        name: &str,
+    ) -> Result<bool> {
+        let snippet = self.snippet(language, name)?;
+        let code = read_to_string(path)?;
+        let inserted = insert_snippet(&code, language, &snippet)
+            .ok_or_else(|| NoSnippetMarker(path.display().to_string(), marker(language)))?;
+
+        if inserted == code {
+            return Ok(false);
+        }
+        fs::write(path, inserted)?;
+
+        Ok(true)
+    }
+}
+
+fn marker(language: &Languages) -> String {
+    format!("{} {}", language.comment(), MARKER)
+}
+
+/// Inserts a snippet above the marker comment, indented like the marker, so
+/// later snippets go below earlier ones. Snippets already in the code are
+/// left out. Returns `None` when there is no marker.
+fn insert_snippet(code: &str, language: &Languages, snippet: &str) -> Option<String> {
+    let marker = marker(language);
+    let line = code.lines().find(|line| line.trim() == marker)?;
+
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let block: String = snippet
+        .trim_end()
+        .lines()
+        .map(|l| match l {
+            "" => "\n".to_string(),
+            l => format!("{}{}\n", indent, l),
+        })
+        .collect();
+    if code.contains(block.trim()) {
+        return Some(code.to_string());
+    }
+
+    let mut inserted = String::with_capacity(code.len() + block.len());
+    let mut done = false;
+    for current in code.split_inclusive('\n') {
+        if !done && current.trim() == marker {
+            inserted.push_str(&block);
+            inserted.push('\n');
+            done = true;
+        }
+        inserted.push_str(current);
+    }
+
+    Some(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts_snippets_at_the_marker() {
+        let code = "public class Main {\n    // kah: snippets\n}\n";
+        let snippet = "static int gcd(int a, int b) {\n    return b == 0 ? a : gcd(b, a % b);\n}\n";
+
+        let inserted = insert_snippet(code, &Languages::Java, snippet).unwrap();
+        assert_eq!(
+            inserted,
+            "public class Main {\n    static int gcd(int a, int b) {\n        \
+             return b == 0 ? a : gcd(b, a % b);\n    }\n\n    // kah: snippets\n}\n"
+        );
+        assert_eq!(
+            insert_snippet(&inserted, &Languages::Java, snippet).unwrap(),
+            inserted
+        );
+    }
+
+    #[test]
+    fn needs_a_marker() {
+        assert!(insert_snippet("print(1)\n", &Languages::Python, "x = 1").is_none());
+        assert_eq!(
+            insert_snippet("# kah: snippets\nprint(1)", &Languages::Python, "x = 1\n").unwrap(),
+            "x = 1\n\n# kah: snippets\nprint(1)"
+        );
+    }
+}
@@ -0,0 +1,750 @@
+use select::{
+    document::Document,
+    node::Node,
+    predicate::{Name, Predicate},
+};
+use std::collections::HashMap;
+
+const WIDTH: usize = 80;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    Text,
+    Markdown,
+}
+
+/// Converts the HTML of a problem statement into readable text for the
+/// terminal, with LaTeX math rendered as Unicode where possible.
+pub(crate) fn to_text(html: &str) -> String {
+    render(html, Format::Text, &HashMap::new())
+}
+
+/// Converts the HTML of a problem statement into Markdown, LaTeX math is kept
+/// as is. Image sources are replaced by their entry in `images`, if any.
+pub(crate) fn to_markdown(html: &str, images: &HashMap<String, String>) -> String {
+    render(html, Format::Markdown, images)
+}
+
+/// Returns the sources of all images in a statement.
+pub(crate) fn image_sources(html: &str) -> Vec<String> {
+    Document::from(html)
+        .find(Name("img").and(|n: &Node| n.attr("src").is_some()))
+        .filter_map(|n| n.attr("src"))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn render(html: &str, format: Format, images: &HashMap<String, String>) -> String {
+    let document = Document::from(html);
+    let renderer = Renderer {
+        format,
+        width: WIDTH,
+        images,
+    };
+
+    let blocks: Vec<_> = document
+        .nth(0)
+        .map(|root| renderer.blocks(&root))
+        .unwrap_or_default();
+
+    blocks.join("\n\n")
+}
+
+struct Renderer<'a> {
+    format: Format,
+    width: usize,
+    images: &'a HashMap<String, String>,
+}
+
+impl<'a> Renderer<'a> {
+    /// Renders the children of `node` as a list of blocks, inline content in
+    /// between block elements becomes its own paragraph.
+    fn blocks(&self, node: &Node) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+
+        for child in node.children() {
+            if is_block(&child) {
+                self.paragraph(&mut inline, &mut blocks);
+                blocks.extend(self.block(&child));
+            } else {
+                inline.push_str(&self.inline(&child));
+            }
+        }
+        self.paragraph(&mut inline, &mut blocks);
+
+        blocks
+    }
+
+    fn block(&self, node: &Node) -> Vec<String> {
+        match node.name() {
+            Some(h @ "h1") | Some(h @ "h2") | Some(h @ "h3") | Some(h @ "h4") | Some(h @ "h5")
+            | Some(h @ "h6") => {
+                let title = collapse_whitespace(&self.inline(node));
+                match self.format {
+                    Format::Text => {
+                        let title = latex_to_unicode(&title);
+                        vec![format!("{}\n{}", title, "-".repeat(title.chars().count()))]
+                    }
+                    Format::Markdown => {
+                        let level = h[1..].parse().unwrap_or(1);
+                        vec![format!("{} {}", "#".repeat(level), title)]
+                    }
+                }
+            }
+            Some("ul") | Some("ol") => vec![self.list(node)],
+            Some("pre") => vec![self.code(node)],
+            Some("table") => vec![self.table(node)],
+            Some("img") => vec![self.image(node)],
+            Some("hr") => match self.format {
+                Format::Text => vec!["-".repeat(self.width)],
+                Format::Markdown => vec!["---".to_string()],
+            },
+            Some("script") | Some("style") | Some("head") => Vec::new(),
+            _ => self.blocks(node),
+        }
+    }
+
+    fn inline(&self, node: &Node) -> String {
+        // Line breaks in the HTML are only whitespace, `br` makes real ones
+        if let Some(text) = node.as_text() {
+            return text.replace(['\r', '\n'], " ");
+        }
+
+        let content = || -> String { node.children().map(|c| self.inline(&c)).collect() };
+        match (self.format, node.name()) {
+            (_, Some("br")) => "\n".to_string(),
+            (_, Some("img")) => self.image(node),
+            (_, Some("script")) | (_, Some("style")) => String::new(),
+            (Format::Markdown, Some("em")) | (Format::Markdown, Some("i")) => {
+                emphasize("*", &content())
+            }
+            (Format::Markdown, Some("strong")) | (Format::Markdown, Some("b")) => {
+                emphasize("**", &content())
+            }
+            (Format::Markdown, Some("code")) | (Format::Markdown, Some("tt")) => {
+                emphasize("`", &content())
+            }
+            (Format::Markdown, Some("a")) => match node.attr("href") {
+                Some(href) => format!("[{}]({})", content(), href),
+                None => content(),
+            },
+            _ => content(),
+        }
+    }
+
+    fn paragraph(&self, inline: &mut String, blocks: &mut Vec<String>) {
+        let text = match self.format {
+            Format::Text => latex_to_unicode(inline),
+            Format::Markdown => inline.clone(),
+        };
+        let lines: Vec<_> = text
+            .lines()
+            .map(collapse_whitespace)
+            .filter(|l| !l.is_empty())
+            .map(|l| match self.format {
+                Format::Text => wrap(&l, self.width),
+                Format::Markdown => l,
+            })
+            .collect();
+
+        if !lines.is_empty() {
+            let separator = match self.format {
+                Format::Text => "\n",
+                // A hard line break in Markdown
+                Format::Markdown => "  \n",
+            };
+            blocks.push(lines.join(separator));
+        }
+        inline.clear();
+    }
+
+    fn list(&self, node: &Node) -> String {
+        let ordered = node.name() == Some("ol");
+        let items = node.children().filter(|c| c.name() == Some("li"));
+
+        items
+            .enumerate()
+            .map(|(i, item)| {
+                let bullet = match (ordered, self.format) {
+                    (true, _) => format!("{}. ", i + 1),
+                    (false, Format::Text) => "• ".to_string(),
+                    (false, Format::Markdown) => "- ".to_string(),
+                };
+                let indent = " ".repeat(bullet.chars().count());
+                let renderer = Renderer {
+                    format: self.format,
+                    width: self.width.saturating_sub(indent.len()).max(20),
+                    images: self.images,
+                };
+
+                renderer
+                    .blocks(&item)
+                    .join("\n")
+                    .lines()
+                    .enumerate()
+                    .map(|(j, line)| match (j, line.is_empty()) {
+                        (0, _) => format!("{}{}", bullet, line),
+                        (_, true) => String::new(),
+                        _ => format!("{}{}", indent, line),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn code(&self, node: &Node) -> String {
+        let code = node.text();
+        let code = code.trim_matches('\n');
+
+        match self.format {
+            Format::Text => code
+                .lines()
+                .map(|l| format!("    {}", l))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Format::Markdown => format!("```\n{}\n```", code),
+        }
+    }
+
+    fn table(&self, node: &Node) -> String {
+        let rows = table_rows(node, |cell| {
+            let cell = collapse_whitespace(&self.inline(cell));
+            match self.format {
+                Format::Text => latex_to_unicode(&cell),
+                Format::Markdown => cell.replace('|', "\\|"),
+            }
+        });
+        let widths = column_widths(&rows);
+
+        let format_row = |row: &[String]| {
+            let cells: Vec<_> = widths
+                .iter()
+                .enumerate()
+                .map(|(i, width)| {
+                    let cell = row.get(i).map(|c| c.as_str()).unwrap_or("");
+                    format!("{: <width$}", cell, width = width)
+                })
+                .collect();
+
+            match self.format {
+                Format::Text => cells.join(" | ").trim_end().to_string(),
+                Format::Markdown => format!("| {} |", cells.join(" | ")),
+            }
+        };
+
+        let mut lines: Vec<_> = rows.iter().map(|r| format_row(r)).collect();
+        if self.format == Format::Markdown && !lines.is_empty() {
+            let separator: Vec<_> = widths.iter().map(|w| "-".repeat((*w).max(3))).collect();
+            lines.insert(1, format!("| {} |", separator.join(" | ")));
+        }
+
+        lines.join("\n")
+    }
+
+    fn image(&self, node: &Node) -> String {
+        let alt = node.attr("alt").filter(|a| !a.is_empty());
+
+        match self.format {
+            Format::Text => match alt {
+                Some(alt) => format!("[image: {}]", alt),
+                None => "[image]".to_string(),
+            },
+            Format::Markdown => {
+                let src = node.attr("src").unwrap_or("");
+                let src = self.images.get(src).map(|s| s.as_str()).unwrap_or(src);
+                format!("![{}]({})", alt.unwrap_or(""), src)
+            }
+        }
+    }
+}
+
+fn emphasize(marker: &str, text: &str) -> String {
+    if text.trim().is_empty() {
+        text.to_string()
+    } else {
+        format!("{}{}{}", marker, text.trim(), marker)
+    }
+}
+
+fn is_block(node: &Node) -> bool {
+    matches!(
+        node.name(),
+        Some("p")
+            | Some("div")
+            | Some("section")
+            | Some("center")
+            | Some("blockquote")
+            | Some("figure")
+            | Some("figcaption")
+            | Some("h1")
+            | Some("h2")
+            | Some("h3")
+            | Some("h4")
+            | Some("h5")
+            | Some("h6")
+            | Some("ul")
+            | Some("ol")
+            | Some("li")
+            | Some("pre")
+            | Some("table")
+            | Some("hr")
+            | Some("html")
+            | Some("body")
+            | Some("head")
+            | Some("script")
+            | Some("style")
+    )
+}
+
+/// Collects the cells of every row in a table, regardless of whether they
+/// are wrapped in `thead`/`tbody`.
+fn table_rows<F: Fn(&Node) -> String>(table: &Node, cell: F) -> Vec<Vec<String>> {
+    table
+        .descendants()
+        .filter(|n| n.name() == Some("tr"))
+        .map(|row| {
+            row.children()
+                .filter(|c| c.name() == Some("td") || c.name() == Some("th"))
+                .map(|c| cell(&c))
+                .collect()
+        })
+        .collect()
+}
+
+fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+
+    (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|r| r.get(i))
+                .map(|c| c.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn wrap(text: &str, width: usize) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+/// Replaces LaTeX math delimited by `$...$`, `$$...$$`, `\(...\)` or
+/// `\[...\]` with a plain Unicode approximation.
+pub(crate) fn latex_to_unicode(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some((start, open, close)) = find_math(rest) {
+        out.push_str(&rest[..start]);
+        let math = &rest[start + open.len()..];
+
+        match math.find(close) {
+            Some(end) => {
+                out.push_str(&convert_math(&math[..end]));
+                rest = &math[end + close.len()..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
+
+fn find_math(text: &str) -> Option<(usize, &'static str, &'static str)> {
+    [("$$", "$$"), ("$", "$"), ("\\(", "\\)"), ("\\[", "\\]")]
+        .iter()
+        .filter_map(|(open, close)| text.find(open).map(|i| (i, *open, *close)))
+        // `$$` and `$` start at the same place, prefer the longest delimiter
+        .min_by_key(|(i, open, _)| (*i, std::cmp::Reverse(open.len())))
+}
+
+fn convert_math(math: &str) -> String {
+    let chars: Vec<char> = math.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                let (command, next) = read_command(&chars, i + 1);
+                i = next;
+                match command.as_str() {
+                    "frac" | "dfrac" | "tfrac" => {
+                        let (numerator, next) = read_group(&chars, i);
+                        let (denominator, next) = read_group(&chars, next);
+                        i = next;
+                        out.push_str(&format!(
+                            "{}/{}",
+                            parenthesize(&convert_math(&numerator)),
+                            parenthesize(&convert_math(&denominator))
+                        ));
+                    }
+                    "sqrt" => {
+                        let (radicand, next) = read_group(&chars, i);
+                        i = next;
+                        out.push('√');
+                        out.push_str(&parenthesize(&convert_math(&radicand)));
+                    }
+                    "text" | "textrm" | "textit" | "textbf" | "mathrm" | "mathit" | "mathbf"
+                    | "mathcal" | "mathbb" | "operatorname" | "texttt" | "mbox" => {
+                        let (content, next) = read_group(&chars, i);
+                        i = next;
+                        out.push_str(&convert_math(&content));
+                    }
+                    "left" | "right" | "big" | "Big" | "bigg" | "Bigg" | "displaystyle" => {}
+                    command => out.push_str(symbol(command).unwrap_or(command)),
+                }
+            }
+            c @ '^' | c @ '_' => {
+                let (script, next) = read_group(&chars, i + 1);
+                i = next;
+                let script = convert_math(&script);
+                let mapped: Option<String> = script
+                    .chars()
+                    .map(|s| {
+                        if c == '^' {
+                            superscript(s)
+                        } else {
+                            subscript(s)
+                        }
+                    })
+                    .collect();
+                match mapped {
+                    Some(mapped) => out.push_str(&mapped),
+                    None => {
+                        out.push(c);
+                        out.push_str(&parenthesize(&script));
+                    }
+                }
+            }
+            '{' | '}' => i += 1,
+            '~' => {
+                out.push(' ');
+                i += 1;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// Reads the name of a command starting after its backslash, either a run of
+/// letters or a single other character like `\{` or `\,`.
+fn read_command(chars: &[char], start: usize) -> (String, usize) {
+    let letters: String = chars[start..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+
+    if letters.is_empty() {
+        match chars.get(start) {
+            Some(c) => (c.to_string(), start + 1),
+            None => (String::new(), start),
+        }
+    } else {
+        let end = start + letters.len();
+        (letters, end)
+    }
+}
+
+/// Reads a `{...}` group or a single token, returning its content and the
+/// index after it.
+fn read_group(chars: &[char], start: usize) -> (String, usize) {
+    let mut i = start;
+    while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+        i += 1;
+    }
+
+    match chars.get(i) {
+        Some('{') => {
+            let mut depth = 0;
+            for (j, c) in chars.iter().enumerate().skip(i) {
+                match c {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return (chars[i + 1..j].iter().collect(), j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (chars[i + 1..].iter().collect(), chars.len())
+        }
+        Some('\\') => {
+            let (command, next) = read_command(chars, i + 1);
+            (format!("\\{}", command), next)
+        }
+        Some(c) => (c.to_string(), i + 1),
+        None => (String::new(), i),
+    }
+}
+
+fn parenthesize(text: &str) -> String {
+    if text.chars().count() <= 1 || text.chars().all(|c| c.is_alphanumeric()) {
+        text.to_string()
+    } else {
+        format!("({})", text)
+    }
+}
+
+fn symbol(command: &str) -> Option<&'static str> {
+    let symbol = match command {
+        "le" | "leq" => "≤",
+        "ge" | "geq" => "≥",
+        "ne" | "neq" => "≠",
+        "lt" => "<",
+        "gt" => ">",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "cdot" => "·",
+        "times" => "×",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "ldots" | "dots" | "cdots" => "…",
+        "infty" => "∞",
+        "sum" => "∑",
+        "prod" => "∏",
+        "int" => "∫",
+        "in" => "∈",
+        "notin" => "∉",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "cup" => "∪",
+        "cap" => "∩",
+        "emptyset" => "∅",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "wedge" | "land" => "∧",
+        "vee" | "lor" => "∨",
+        "oplus" => "⊕",
+        "to" | "rightarrow" => "→",
+        "leftarrow" => "←",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftrightarrow" | "iff" => "⇔",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "mid" => "|",
+        "circ" => "∘",
+        "degree" => "°",
+        "prime" => "′",
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "Delta" => "Δ",
+        "epsilon" | "varepsilon" => "ε",
+        "theta" => "θ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "pi" => "π",
+        "rho" => "ρ",
+        "sigma" => "σ",
+        "Sigma" => "Σ",
+        "tau" => "τ",
+        "phi" | "varphi" => "φ",
+        "omega" => "ω",
+        "Omega" => "Ω",
+        "log" => "log",
+        "ln" => "ln",
+        "max" => "max",
+        "min" => "min",
+        "gcd" => "gcd",
+        "bmod" | "mod" => " mod ",
+        "quad" | "qquad" => "  ",
+        "," | ";" | ":" | " " => " ",
+        "!" => "",
+        "{" => "{",
+        "}" => "}",
+        "%" => "%",
+        "$" => "$",
+        "#" => "#",
+        "&" => "&",
+        "_" => "_",
+        "\\" => "\n",
+        _ => return None,
+    };
+
+    Some(symbol)
+}
+
+fn superscript(c: char) -> Option<char> {
+    let mapped = match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'n' => 'ⁿ',
+        'i' => 'ⁱ',
+        _ => return None,
+    };
+
+    Some(mapped)
+}
+
+fn subscript(c: char) -> Option<char> {
+    let mapped = match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'n' => 'ₙ',
+        'm' => 'ₘ',
+        'x' => 'ₓ',
+        _ => return None,
+    };
+
+    Some(mapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = include_str!("../tests/fixtures/statement.html");
+    const OLD_LAYOUT: &str = include_str!("../tests/fixtures/old_layout.html");
+
+    #[test]
+    fn renders_statements_as_text() {
+        assert_eq!(
+            to_text(STATEMENT),
+            "Input\n-----\n\n\
+             The first line contains two integers N and M (1 ≤ N ≤ 10⁵, 0 ≥ -M). Then follow\n\
+             N lines with the values a₁, …, a_N.\n\n\
+             The answer is at most (N(N-1))/2, which fits in a 64-bit integer.\n\n\
+             1. Read the input.\n\
+             2. For every value:\n   \
+                • add it to the sum,\n   \
+                • keep the largest one.\n\
+             3. Print the answer.\n\n\
+             Group | Points | Constraints\n\
+             1     | 20     | N ≤ 100\n\
+             2     | 80     | No further constraints\n\n\
+             [image: A sum]"
+        );
+
+        let hello = to_text(OLD_LAYOUT);
+        assert!(hello.contains("Output\n------\n\nOutput should contain one line"));
+    }
+
+    #[test]
+    fn renders_statements_as_markdown() {
+        let images = HashMap::from([(
+            "/problems/sum/file/statement/en/img-0001.png".to_string(),
+            "img-0001.png".to_string(),
+        )]);
+
+        assert_eq!(
+            to_markdown(STATEMENT, &images),
+            "## Input\n\n\
+             The first line contains two integers $N$ and $M$ ($1 \\le N \\le 10^5$, $0 \\ge -M$). \
+             Then follow $N$ lines with the values $a_1, \\ldots, a_N$.\n\n\
+             The answer is at most $\\frac{N(N-1)}{2}$, which fits in a 64-bit integer.\n\n\
+             1. Read the input.\n\
+             2. For every value:\n   \
+                - add it to the sum,\n   \
+                - keep the largest one.\n\
+             3. Print the answer.\n\n\
+             | Group | Points | Constraints            |\n\
+             | ----- | ------ | ---------------------- |\n\
+             | 1     | 20     | $N \\le 100$            |\n\
+             | 2     | 80     | No further constraints |\n\n\
+             ![A sum](img-0001.png)"
+        );
+        assert_eq!(
+            image_sources(STATEMENT),
+            vec!["/problems/sum/file/statement/en/img-0001.png"]
+        );
+    }
+
+    #[test]
+    fn converts_math() {
+        assert_eq!(latex_to_unicode("$x_1^2 + x_{10}^{n+1}$"), "x₁² + x₁₀ⁿ⁺¹");
+        assert_eq!(latex_to_unicode("$2^{y}$ and $a_{b}$"), "2^y and a_b");
+        assert_eq!(latex_to_unicode("\\(\\frac{1}{2}\\)"), "1/2");
+        assert_eq!(latex_to_unicode("$\\frac{a+b}{c}$"), "(a+b)/c");
+        assert_eq!(
+            latex_to_unicode("$$1 \\le N \\leq 100$$, $M \\ge 0$"),
+            "1 ≤ N ≤ 100, M ≥ 0"
+        );
+        assert_eq!(convert_math("\\sqrt{n} \\cdot \\pi"), "√n · π");
+        assert_eq!(latex_to_unicode("costs $5 each"), "costs $5 each");
+    }
+
+    #[test]
+    fn wraps_at_the_width() {
+        assert_eq!(wrap("one two three four", 9), "one two\nthree\nfour");
+        assert_eq!(wrap("unbreakablewords stay", 5), "unbreakablewords\nstay");
+
+        let long = format!("<p>{}</p>", "word ".repeat(100));
+        let text = to_text(&long);
+        assert!(text.lines().count() > 1);
+        assert!(text.lines().all(|l| l.chars().count() <= WIDTH));
+    }
+}
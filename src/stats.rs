@@ -0,0 +1,263 @@
+use crate::{datafile::Problem, languages::Languages};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Formatter},
+};
+
+const BAR_WIDTH: usize = 30;
+const WEEKS: i64 = 8;
+
+#[derive(Debug)]
+pub(crate) struct Stats {
+    tracked: usize,
+    solved: usize,
+    score: f32,
+    languages: Vec<(Languages, usize, usize)>,
+    difficulties: BTreeMap<u32, (usize, usize)>,
+    weeks: Vec<(NaiveDate, usize)>,
+    current_streak: usize,
+    longest_streak: usize,
+}
+
+impl Stats {
+    pub(crate) fn new(problems: &[Problem], today: NaiveDate) -> Self {
+        let solved: Vec<_> = problems.iter().filter(|p| p.solution.solved).collect();
+
+        let mut languages: HashMap<Languages, (usize, usize)> = HashMap::new();
+        let mut difficulties = BTreeMap::new();
+        for problem in problems {
+            let solved = problem.solution.solved as usize;
+
+            let language = languages
+                .entry(problem.solution.language.clone())
+                .or_default();
+            language.0 += 1;
+            language.1 += solved;
+
+            let bucket = difficulties
+                .entry(problem.metadata.difficulty.max(0.0).floor() as u32)
+                .or_insert((0, 0));
+            bucket.0 += 1;
+            bucket.1 += solved;
+        }
+
+        let mut languages: Vec<_> = languages.into_iter().map(|(l, (t, s))| (l, t, s)).collect();
+        languages.sort_unstable_by(|(l, t, _), (o, u, _)| {
+            u.cmp(t).then_with(|| l.to_string().cmp(&o.to_string()))
+        });
+
+        let dates: Vec<NaiveDate> = solved
+            .iter()
+            .filter_map(|p| p.solution.solved_at)
+            .map(|d| d.with_timezone(&Local).date_naive())
+            .collect();
+        let days: BTreeSet<NaiveDate> = dates.iter().cloned().collect();
+
+        let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let weeks = (0..WEEKS)
+            .rev()
+            .map(|w| {
+                let start = week_start - Duration::weeks(w);
+                let end = start + Duration::weeks(1);
+                let count = dates.iter().filter(|d| **d >= start && **d < end).count();
+                (start, count)
+            })
+            .collect();
+
+        Stats {
+            tracked: problems.len(),
+            solved: solved.len(),
+            score: solved.iter().map(|p| p.metadata.difficulty).sum(),
+            languages,
+            difficulties,
+            weeks,
+            current_streak: current_streak(&days, today),
+            longest_streak: longest_streak(&days),
+        }
+    }
+}
+
+/// Counts the consecutive days with a solve ending today, or yesterday if
+/// nothing has been solved yet today.
+fn current_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+
+    streak
+}
+
+fn longest_streak(days: &BTreeSet<NaiveDate>) -> usize {
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for day in days {
+        streak = match previous {
+            Some(previous) if *day - previous == Duration::days(1) => streak + 1,
+            _ => 1,
+        };
+        longest = longest.max(streak);
+        previous = Some(*day);
+    }
+
+    longest
+}
+
+fn bar(value: usize, max: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+
+    "█".repeat((value * BAR_WIDTH).div_ceil(max))
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Tracked: {}, solved: {}", self.tracked, self.solved)?;
+        writeln!(f, "Score (approx.): {:.1}", self.score)?;
+        writeln!(
+            f,
+            "Streak: {} day(s), longest: {} day(s)",
+            self.current_streak, self.longest_streak
+        )?;
+
+        writeln!(f, "\nSolved per language")?;
+        let max = self.languages.iter().map(|(_, t, _)| *t).max().unwrap_or(0);
+        for (language, tracked, solved) in &self.languages {
+            writeln!(
+                f,
+                "{: <10} {: >4}/{: <4} {}",
+                language.to_string(),
+                solved,
+                tracked,
+                bar(*solved, max)
+            )?;
+        }
+
+        writeln!(f, "\nSolved per difficulty")?;
+        let max = self
+            .difficulties
+            .values()
+            .map(|(t, _)| *t)
+            .max()
+            .unwrap_or(0);
+        for (difficulty, (tracked, solved)) in &self.difficulties {
+            writeln!(
+                f,
+                "{: <10} {: >4}/{: <4} {}",
+                format!("{}.0-{}.9", difficulty, difficulty),
+                solved,
+                tracked,
+                bar(*solved, max)
+            )?;
+        }
+
+        writeln!(f, "\nSolved per week")?;
+        let max = self.weeks.iter().map(|(_, c)| *c).max().unwrap_or(0);
+        for (week, count) in &self.weeks {
+            writeln!(
+                f,
+                "{: <10} {: >4}      {}",
+                week.format("%Y-%m-%d").to_string(),
+                count,
+                bar(*count, max)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{datafile::Solution, problem::ProblemMetadata};
+    use chrono::{TimeZone, Utc};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2021, 3, day).unwrap()
+    }
+
+    fn days(days: &[u32]) -> BTreeSet<NaiveDate> {
+        days.iter().map(|d| date(*d)).collect()
+    }
+
+    fn problem(id: &str, difficulty: f32, solved_on: Option<u32>) -> Problem {
+        let solved_at = solved_on.map(|day| {
+            let noon = date(day).and_hms_opt(12, 0, 0).unwrap();
+            Local
+                .from_local_datetime(&noon)
+                .unwrap()
+                .with_timezone(&Utc)
+        });
+
+        Problem {
+            metadata: ProblemMetadata {
+                name: id.to_string(),
+                id: id.to_string(),
+                cpu_time_limit: "1 second".to_string(),
+                memory_limit: "1024 MB".to_string(),
+                difficulty,
+                samples: Vec::new(),
+                statement: None,
+            },
+            solution: Solution {
+                language: Languages::Python,
+                solved: solved_on.is_some(),
+                touched: None,
+                solved_at,
+                path: None,
+                tested: None,
+                tests: None,
+                statement: None,
+            },
+        }
+    }
+
+    #[test]
+    fn counts_current_streaks() {
+        // Ending today, or yesterday when nothing is solved yet today
+        assert_eq!(current_streak(&days(&[8, 9, 10]), date(10)), 3);
+        assert_eq!(current_streak(&days(&[8, 9]), date(10)), 2);
+        assert_eq!(current_streak(&days(&[7, 8]), date(10)), 0);
+        assert_eq!(current_streak(&days(&[5, 6, 8, 10]), date(10)), 1);
+        assert_eq!(current_streak(&BTreeSet::new(), date(10)), 0);
+    }
+
+    #[test]
+    fn counts_longest_streaks() {
+        assert_eq!(longest_streak(&days(&[1, 2, 3, 5, 6, 9])), 3);
+        assert_eq!(longest_streak(&days(&[1, 3, 5])), 1);
+        assert_eq!(longest_streak(&BTreeSet::new()), 0);
+    }
+
+    #[test]
+    fn buckets_difficulties() {
+        let problems = vec![
+            problem("a", 1.2, Some(9)),
+            problem("b", 1.9, None),
+            problem("c", 2.0, Some(10)),
+            problem("d", 4.5, None),
+        ];
+
+        let stats = Stats::new(&problems, date(10));
+        assert_eq!(
+            stats.difficulties,
+            BTreeMap::from([(1, (2, 1)), (2, (1, 1)), (4, (1, 0))])
+        );
+        assert_eq!((stats.tracked, stats.solved), (4, 2));
+        assert!((stats.score - 3.2).abs() < 1e-4);
+        assert_eq!((stats.current_streak, stats.longest_streak), (2, 2));
+        assert!(stats.to_string().contains("1.0-1.9       1/2"));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    contest::{problem_id, Contest},
+    datafile::TestRun,
+    kah::Kah,
+    problem::collapse_whitespace,
+    utils::table,
+};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use select::{
+    document::Document,
+    predicate::{Attr, Name, Predicate},
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Formatter},
+};
+
+const SCOREBOARD_ROWS: usize = 10;
+const ACCEPTED: &str = "Accepted";
+const VERDICTS: &[&str] = &[
+    ACCEPTED,
+    "Wrong Answer",
+    "Time Limit Exceeded",
+    "Memory Limit Exceeded",
+    "Output Limit Exceeded",
+    "Run Time Error",
+    "Compile Error",
+    "Judge Error",
+    "New",
+    "Compiling",
+    "Running",
+];
+
+/// A snapshot of how a contest is going: the time left, our local test runs,
+/// our verdicts on Kattis and the scoreboard.
+#[derive(Debug)]
+pub(crate) struct ContestStatus {
+    contest: Contest,
+    username: String,
+    now: DateTime<Utc>,
+    tests: HashMap<String, TestRun>,
+    verdicts: Result<HashMap<String, String>, String>,
+    scoreboard: Result<Vec<Vec<String>>, String>,
+}
+
+impl ContestStatus {
+    /// Fetches our submissions and the scoreboard of a contest. Pages that
+    /// cannot be fetched are shown as unavailable instead of failing.
+    pub(crate) fn fetch(kah: &Kah, contest: Contest) -> Result<Self> {
+        let tests = kah
+            .problems()?
+            .into_iter()
+            .filter_map(|p| Some((p.metadata.id, p.solution.tested?)))
+            .collect();
+
+        let page = |page: &str| {
+            kah.client
+                .get_text(&format!("{}/{}", contest.base_url(), page))
+                .map_err(|e| e.to_string())
+        };
+        let verdicts = page("submissions").map(|b| parse_verdicts(&b));
+        let scoreboard = page("standings").map(|b| parse_scoreboard(&b));
+
+        Ok(ContestStatus {
+            contest,
+            username: kah.kattis.username.clone(),
+            now: Utc::now(),
+            tests,
+            verdicts,
+            scoreboard,
+        })
+    }
+
+    fn countdown(&self) -> String {
+        match (self.contest.start, self.contest.end) {
+            (Some(start), _) if self.now < start => {
+                format!("Starts in {}", format_duration(start - self.now))
+            }
+            (_, Some(end)) if self.now < end => {
+                format!("Time remaining: {}", format_duration(end - self.now))
+            }
+            (_, Some(_)) => "The contest is over".to_string(),
+            (_, None) => "No end time".to_string(),
+        }
+    }
+
+    /// The scoreboard is cut off after the first rows, but our own row is
+    /// always shown.
+    fn scoreboard_rows(&self, rows: &[Vec<String>]) -> Vec<Vec<String>> {
+        let mut shown: Vec<_> = rows.iter().take(SCOREBOARD_ROWS).cloned().collect();
+        if let Some(row) = rows
+            .iter()
+            .skip(SCOREBOARD_ROWS)
+            .find(|r| r.iter().any(|c| c.contains(&self.username)))
+        {
+            shown.push(vec!["...".to_string()]);
+            shown.push(row.clone());
+        }
+
+        shown
+    }
+}
+
+impl fmt::Display for ContestStatus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "{} ({})", self.contest.name, self.contest.id)?;
+        writeln!(f, "{}", self.countdown())?;
+
+        let rows = self
+            .contest
+            .problems
+            .iter()
+            .map(|problem| {
+                let local = match self.tests.get(&problem.id) {
+                    Some(run) => format!(
+                        "{}/{} {}",
+                        run.passed,
+                        run.total,
+                        if run.ok() { "OK" } else { "FAIL" }
+                    ),
+                    None => "-".to_string(),
+                };
+                let verdict = match &self.verdicts {
+                    Ok(verdicts) => verdicts.get(&problem.id).map(String::as_str),
+                    Err(_) => None,
+                };
+
+                vec![
+                    problem.letter.clone(),
+                    problem.id.clone(),
+                    local,
+                    verdict.unwrap_or("-").to_string(),
+                ]
+            })
+            .collect();
+        writeln!(f, "\n{}", table(&["", "ID", "Local", "Kattis"], rows))?;
+        if let Err(err) = &self.verdicts {
+            writeln!(f, "Submissions unavailable: {}", err)?;
+        }
+
+        writeln!(f, "\nScoreboard")?;
+        match &self.scoreboard {
+            Ok(rows) if rows.len() > 1 => {
+                let shown = self.scoreboard_rows(&rows[1..]);
+                writeln!(f, "{}", table(&rows[0], shown))?;
+            }
+            Ok(_) => writeln!(f, "Nobody is on the scoreboard yet")?,
+            Err(err) => writeln!(f, "Unavailable: {}", err)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the verdict of each problem in a list of submissions, newest first.
+/// An accepted submission wins over later ones.
+fn parse_verdicts(body: &str) -> HashMap<String, String> {
+    let document = Document::from(body);
+    let mut verdicts: HashMap<String, String> = HashMap::new();
+
+    for row in document.find(Name("tr")) {
+        let problem = row
+            .find(Name("a").and(Attr("href", ())))
+            .find_map(|a| a.attr("href").and_then(problem_id));
+        let verdict = row
+            .find(Name("td"))
+            .map(|c| collapse_whitespace(&c.text()))
+            .find(|c| VERDICTS.contains(&c.as_str()));
+
+        if let (Some(problem), Some(verdict)) = (problem, verdict) {
+            if verdict == ACCEPTED {
+                verdicts.insert(problem, verdict);
+            } else {
+                verdicts.entry(problem).or_insert(verdict);
+            }
+        }
+    }
+
+    verdicts
+}
+
+/// Reads the first table on the standings page, the first row being the
+/// header.
+fn parse_scoreboard(body: &str) -> Vec<Vec<String>> {
+    let document = Document::from(body);
+    let table = match document.find(Name("table")).next() {
+        Some(table) => table,
+        None => return Vec::new(),
+    };
+
+    table
+        .find(Name("tr"))
+        .map(|row| {
+            row.find(Name("th").or(Name("td")))
+                .map(|c| collapse_whitespace(&c.text()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| row.iter().any(|c| !c.is_empty()))
+        .collect()
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (days, hours) = (seconds / 86400, seconds / 3600 % 24);
+    let (minutes, seconds) = (seconds / 60 % 60, seconds % 60);
+
+    if days > 0 {
+        format!("{}d {:02}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUBMISSIONS: &str = include_str!("../tests/fixtures/contest_submissions.html");
+    const STANDINGS: &str = include_str!("../tests/fixtures/contest_standings.html");
+
+    #[test]
+    fn parses_verdicts() {
+        let verdicts = parse_verdicts(SUBMISSIONS);
+
+        assert_eq!(verdicts["hello"], "Accepted");
+        assert_eq!(verdicts["twostones"], "Wrong Answer");
+        assert!(!verdicts.contains_key("carrots"));
+    }
+
+    #[test]
+    fn parses_scoreboard() {
+        let scoreboard = parse_scoreboard(STANDINGS);
+
+        assert_eq!(scoreboard[0], ["Rank", "Team", "Solved", "Time"]);
+        assert_eq!(scoreboard[2], ["2", "user", "1", "17"]);
+        assert_eq!(scoreboard.len(), 4);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::seconds(3723)), "01:02:03");
+        assert_eq!(format_duration(Duration::seconds(90061)), "1d 01:01:01");
+        assert_eq!(format_duration(Duration::seconds(-5)), "00:00:00");
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    bundle::{bundle, Bundle},
+    contest::Contest,
+    datafile::Problem,
+    error::KahError::{self, NoSamples, SubmitError, TestsFailed},
+    kah::Kah,
+    languages::Languages,
+    leftovers::debug_leftovers,
+    test::Test,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::fs;
+
+/// Submits the solution to a problem and returns the URL of the submission.
+/// While a contest the problem is part of is running, the submission goes to
+/// the contest instead of the problem archive. Solutions with local modules
+/// are bundled into one file, which has to pass the tests first unless
+/// `force` is set.
+pub(crate) fn submit(
+    kah: &Kah,
+    problem: &Problem,
+    language: &Languages,
+    force: bool,
+) -> Result<String> {
+    let file = kah.config.code.join(problem.solution_path());
+    let file_name = file
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let bundle = bundle(&file, language)?;
+    if bundle.is_bundled() {
+        println!("Bundled {} files into {}", bundle.files.len(), file_name);
+    }
+    warn_debug_leftovers(&bundle, language)?;
+    test_submission(kah, problem, language, &file_name, &bundle, force)?;
+    let contents = bundle.code.into_bytes();
+
+    let contest = running_contest(kah.contest()?, &problem.metadata.id, Utc::now());
+    let url = match &contest {
+        Some(contest) => {
+            println!("Submitting {} to {}", problem.metadata.name, contest.name);
+            contest.submit_url()
+        }
+        None => {
+            println!("Submitting {} to Kattis", problem.metadata.name);
+            kah.kattis.submit.clone()
+        }
+    };
+
+    kah.client.login()?;
+    let language_name = language.kattis_name();
+    let main_class = language.main_class(&file);
+    let response = kah.client.post_file(
+        &url,
+        &[
+            ("submit", "true"),
+            ("submit_ctr", "2"),
+            ("language", &language_name),
+            ("mainclass", &main_class),
+            ("problem", &problem.metadata.id),
+            ("tag", ""),
+            ("script", "true"),
+        ],
+        ("sub_file[]", &file_name, &contents),
+    )?;
+
+    let id = submission_id(&response).ok_or_else(|| {
+        let reason = response.lines().next().unwrap_or("").trim().to_string();
+        SubmitError(problem.metadata.id.clone(), reason)
+    })?;
+
+    Ok(submission_url(&kah.get_kattis_url(), contest.as_ref(), &id))
+}
+
+/// The saved contest, if the problem is part of it and it is running.
+fn running_contest(contest: Option<Contest>, id: &str, now: DateTime<Utc>) -> Option<Contest> {
+    contest.filter(|c| c.problem(id).is_some() && c.is_running(now))
+}
+
+/// Tests the solution as it is submitted, failing if a case fails, it does
+/// not compile or there are no samples to test it with unless forced.
+/// Languages that cannot be tested locally are submitted anyway.
+fn test_submission(
+    kah: &Kah,
+    problem: &Problem,
+    language: &Languages,
+    file_name: &str,
+    bundle: &Bundle,
+    force: bool,
+) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut test = Test::new(kah, problem.clone(), false)?.with_language(language);
+    if !test.has_samples() {
+        if force {
+            eprintln!(
+                "{} has no samples, submitting untested",
+                problem.metadata.name
+            );
+            return Ok(());
+        }
+        return Err(NoSamples(problem.metadata.id.clone()).into());
+    }
+    if bundle.is_bundled() {
+        let path = dir.path().join(file_name);
+        fs::write(&path, &bundle.code)?;
+        test = test.with_file(&path);
+    }
+
+    let result = match test.run() {
+        Ok(result) => result,
+        Err(err) if force || matches!(err.downcast_ref(), Some(KahError::CannotTest(_))) => {
+            eprintln!("{}", err);
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
+    kah.record_test(&problem.metadata.id, &result)?;
+
+    match (result.passed(), force) {
+        (true, _) => Ok(()),
+        (false, true) => {
+            eprintln!("Submitting anyway");
+            Ok(())
+        }
+        (false, false) => Err(TestsFailed(problem.metadata.id.clone()).into()),
+    }
+}
+
+fn warn_debug_leftovers(bundle: &Bundle, language: &Languages) -> Result<()> {
+    for file in &bundle.files {
+        for leftover in debug_leftovers(&fs::read_to_string(file)?, language) {
+            eprintln!(
+                "warning: {} {} looks like debugging code",
+                file.display(),
+                leftover
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn submission_url(kattis_url: &str, contest: Option<&Contest>, id: &str) -> String {
+    match contest {
+        Some(contest) => format!("{}/submissions/{}", contest.base_url(), id),
+        None => format!("{}/submissions/{}", kattis_url, id),
+    }
+}
+
+/// Kattis answers with e.g. `Submission received. Submission ID: 123456.`
+fn submission_id(response: &str) -> Option<String> {
+    let (_, rest) = response.split_once("Submission ID:")?;
+    let id: String = rest
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    if id.is_empty() {
+        None
+    } else {
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contest::ContestProblem;
+    use chrono::Duration;
+
+    fn contest(start: DateTime<Utc>, end: DateTime<Utc>) -> Contest {
+        Contest {
+            id: "abc".to_string(),
+            name: "Spring Practice".to_string(),
+            url: "https://open.kattis.com/contests/abc/problems".to_string(),
+            problems: vec![ContestProblem {
+                letter: "A".to_string(),
+                id: "hello".to_string(),
+                name: "Hello World!".to_string(),
+            }],
+            start: Some(start),
+            end: Some(end),
+        }
+    }
+
+    #[test]
+    fn submits_to_running_contests() {
+        let now = Utc::now();
+        let running = contest(now - Duration::hours(1), now + Duration::hours(1));
+        let over = contest(now - Duration::hours(2), now - Duration::hours(1));
+
+        let target = running_contest(Some(running.clone()), "hello", now);
+        assert_eq!(
+            target.as_ref().map(Contest::submit_url).as_deref(),
+            Some("https://open.kattis.com/contests/abc/submit")
+        );
+        assert!(running_contest(Some(running), "twostones", now).is_none());
+        assert!(running_contest(Some(over), "hello", now).is_none());
+        assert!(running_contest(None, "hello", now).is_none());
+
+        assert_eq!(
+            submission_url("https://open.kattis.com", target.as_ref(), "42"),
+            "https://open.kattis.com/contests/abc/submissions/42"
+        );
+        assert_eq!(
+            submission_url("https://open.kattis.com", None, "42"),
+            "https://open.kattis.com/submissions/42"
+        );
+    }
+
+    #[test]
+    fn finds_submission_ids() {
+        assert_eq!(
+            submission_id("Submission received. Submission ID: 4242.").as_deref(),
+            Some("4242")
+        );
+        assert_eq!(submission_id("Submission ID:123"), Some("123".to_string()));
+        assert_eq!(submission_id("You need to log in"), None);
+        assert_eq!(submission_id("Submission ID: none"), None);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{languages::Languages, problem::ProblemMetadata};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::{fs::read_to_string, path::Path};
+
+/// Reads the template for a language from a templates directory, e.g.
+/// `Problem.java`, if there is one.
+pub(crate) fn read_template(dir: &Path, language: &Languages) -> Result<Option<String>> {
+    let template = dir.join(format!("Problem.{}", language.extension()));
+
+    if template.is_file() {
+        Ok(Some(read_to_string(template)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The values placeholders like `{{problem_id}}` in a solution template are
+/// replaced with.
+#[derive(Debug)]
+pub(crate) struct TemplateContext {
+    problem_id: String,
+    problem_name: String,
+    time_limit: String,
+    url: String,
+    date: String,
+    class_name: String,
+}
+
+impl TemplateContext {
+    /// The class name is the name of the solution file, which Java requires
+    /// for public classes and Kotlin uses for its main class.
+    pub(crate) fn new(problem: &ProblemMetadata, url: &str, file: &Path, date: NaiveDate) -> Self {
+        TemplateContext {
+            problem_id: problem.id.clone(),
+            problem_name: problem.name.clone(),
+            time_limit: problem.cpu_time_limit.clone(),
+            url: url.to_string(),
+            date: date.format("%Y-%m-%d").to_string(),
+            class_name: file
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        let value = match name {
+            "problem_id" => &self.problem_id,
+            "problem_name" => &self.problem_name,
+            "time_limit" => &self.time_limit,
+            "url" => &self.url,
+            "date" => &self.date,
+            "class_name" => &self.class_name,
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Replaces the placeholders in a template, unknown ones are left as
+    /// they are.
+    pub(crate) fn render(&self, template: &str) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            rendered.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+
+            match after.find("}}") {
+                Some(end) => {
+                    match self.value(after[..end].trim()) {
+                        Some(value) => rendered.push_str(value),
+                        None => rendered.push_str(&rest[start..start + end + 4]),
+                    }
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    rendered.push_str(&rest[start..]);
+                    rest = "";
+                }
+            }
+        }
+        rendered.push_str(rest);
+
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> TemplateContext {
+        let problem = ProblemMetadata {
+            name: "Hello World!".to_string(),
+            id: "hello".to_string(),
+            cpu_time_limit: "1 second".to_string(),
+            memory_limit: "1024 MB".to_string(),
+            difficulty: 1.2,
+            samples: Vec::new(),
+            statement: None,
+        };
+
+        TemplateContext::new(
+            &problem,
+            "https://open.kattis.com/problems/hello",
+            Path::new("java/HelloWorld.java"),
+            NaiveDate::from_ymd_opt(2021, 3, 6).unwrap(),
+        )
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let template = "// {{problem_name}} ({{ problem_id }}), {{time_limit}}\n\
+                        // {{url}} on {{date}}\n\
+                        public class {{class_name}} {}\n";
+
+        assert_eq!(
+            context().render(template),
+            "// Hello World! (hello), 1 second\n\
+             // https://open.kattis.com/problems/hello on 2021-03-06\n\
+             public class HelloWorld {}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        assert_eq!(
+            context().render("{{unknown}} {{problem_id}} {{"),
+            "{{unknown}} hello {{"
+        );
+    }
+}
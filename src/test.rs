@@ -1,14 +1,18 @@
 use crate::utils::{average_duration, max_duration, min_duration};
-use crate::{datafile::Problem, kah::Kah};
+use crate::{datafile::Problem, kah::Kah, languages::Languages, workspace::read_samples};
 use anyhow::Result;
-use std::{path::PathBuf, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tempfile::tempdir;
 
 #[derive(Debug)]
 pub(crate) struct Test {
     pub(crate) problem: Problem,
     pub(crate) temp_dir: PathBuf,
-    pub(crate) code_dir: PathBuf,
+    /// The solution to test
+    pub(crate) file: PathBuf,
     pub(crate) verbose: bool,
 }
 
@@ -26,6 +30,11 @@ impl TestResult {
         }
     }
 
+    /// Whether every case passed.
+    pub(crate) fn passed(&self) -> bool {
+        self.results.iter().all(|r| *r)
+    }
+
     pub(crate) fn report(&self, test: &Test) {
         println!("{: <10} {: <10} {: <10}", "Case", "Result", "Time");
         println!("{: <10} {: <10} {: <10}", "----", "------", "----");
@@ -59,28 +68,59 @@ impl TestResult {
 }
 
 impl Test {
-    pub(crate) fn new(kah: &Kah, problem: Problem, verbose: bool) -> Self {
-        Test {
+    pub(crate) fn new(kah: &Kah, mut problem: Problem, verbose: bool) -> Result<Self> {
+        if let Some(workspace) = &kah.workspace {
+            let samples = workspace.samples(&problem.metadata.id)?;
+            problem.metadata.samples.extend(samples);
+        }
+        if let Some(tests) = &problem.solution.tests {
+            let samples = read_samples(&kah.config.code.join(tests))?;
+            problem.metadata.samples.extend(samples);
+        }
+
+        Ok(Test {
+            temp_dir: tempdir()?.into_path(),
+            file: kah.config.code.join(problem.solution_path()),
             problem,
-            temp_dir: tempdir().expect("Could not create temp dir").into_path(),
-            code_dir: kah.config.code.clone(),
             verbose,
-        }
+        })
+    }
+
+    /// Tests another file than the solution, e.g. a bundled one.
+    pub(crate) fn with_file(mut self, file: &Path) -> Self {
+        self.file = file.to_owned();
+        self
+    }
+
+    /// Tests the solution as another language than it was created in.
+    pub(crate) fn with_language(mut self, language: &Languages) -> Self {
+        self.problem.solution.language = language.clone();
+        self
+    }
+
+    /// Whether there is anything to test the solution with.
+    pub(crate) fn has_samples(&self) -> bool {
+        !self.problem.metadata.samples.is_empty()
+    }
+
+    /// Where compiled languages put the executable.
+    pub(crate) fn binary(&self) -> PathBuf {
+        self.temp_dir.join("solution")
     }
 
-    pub(crate) fn run(&mut self) -> Result<()> {
+    pub(crate) fn run(&mut self) -> Result<TestResult> {
         self.build_problem()?;
         let result = self.run_tests()?;
         result.report(self);
 
-        Ok(())
+        Ok(result)
     }
 
     fn build_problem(&self) -> Result<()> {
-        self.problem.solution.language.build()
+        self.problem.solution.language.build(self)
     }
 
     fn run_tests(&self) -> Result<TestResult> {
-        self.problem.solution.language.run(&self)
+        self.problem.solution.language.run(self)
     }
 }
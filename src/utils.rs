@@ -1,6 +1,18 @@
-use crate::problem::Sample;
+use crate::{
+    error::KahError::{EditorError, NoEditor},
+    problem::Sample,
+};
 use anyhow::Result;
-use std::{fs::File, io::Read, path::PathBuf, time::Duration};
+use std::{
+    cmp::Reverse,
+    env, fs,
+    fs::File,
+    io::{stdout, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+use tempfile::NamedTempFile;
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
 struct ZipFile {
@@ -9,7 +21,7 @@ struct ZipFile {
 }
 
 pub(crate) fn unzip(file_name: &PathBuf) -> Result<Vec<Sample>> {
-    let file = File::open(&file_name)?;
+    let file = File::open(file_name)?;
     let mut archive = zip::ZipArchive::new(file)?;
 
     let mut files = Vec::new();
@@ -64,3 +76,248 @@ pub(crate) fn max_duration(timings: &[Duration]) -> String {
 fn format_duration(duration: Duration) -> String {
     format!("{:.2?}s", duration.as_secs_f64())
 }
+
+/// How a query matches, from worst to best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum MatchTier {
+    Subsequence,
+    Substring,
+    Prefix,
+    Exact,
+}
+
+/// How well a query matches, ordered by tier and then by how few characters
+/// the candidate has beyond the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FuzzyScore {
+    pub(crate) tier: MatchTier,
+    pub(crate) extra: Reverse<usize>,
+}
+
+impl FuzzyScore {
+    pub(crate) fn exact() -> Self {
+        FuzzyScore {
+            tier: MatchTier::Exact,
+            extra: Reverse(0),
+        }
+    }
+}
+
+/// Scores how well `query` matches `candidate`, ignoring case, or `None` if
+/// it does not match at all.
+pub(crate) fn fuzzy_score(query: &str, candidate: &str) -> Option<FuzzyScore> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+
+    let tier = if candidate == query {
+        MatchTier::Exact
+    } else if candidate.starts_with(&query) {
+        MatchTier::Prefix
+    } else if candidate.contains(&query) {
+        MatchTier::Substring
+    } else if is_subsequence(&query, &candidate) {
+        MatchTier::Subsequence
+    } else {
+        return None;
+    };
+
+    Some(FuzzyScore {
+        tier,
+        extra: Reverse(candidate.chars().count() - query.chars().count()),
+    })
+}
+
+fn is_subsequence(query: &str, candidate: &str) -> bool {
+    let mut chars = candidate.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+/// Writes a file by writing to a temporary file in the same directory and
+/// renaming it over the old one, so a crash never leaves a half-written file.
+pub(crate) fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(content)?;
+    file.as_file().sync_all()?;
+    file.persist(path)?;
+
+    Ok(())
+}
+
+/// Lays out rows as a table with aligned columns under a header. Rows may
+/// be shorter than the header.
+pub(crate) fn table<S: AsRef<str>>(header: &[S], rows: Vec<Vec<String>>) -> String {
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            rows.iter()
+                .filter_map(|r| r.get(i))
+                .map(|c| c.chars().count())
+                .chain(std::iter::once(h.as_ref().chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |row: Vec<String>| {
+        row.iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{: <width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![
+        format_row(header.iter().map(|h| h.as_ref().to_string()).collect()),
+        format_row(
+            header
+                .iter()
+                .map(|h| "-".repeat(h.as_ref().chars().count()))
+                .collect(),
+        ),
+    ];
+    lines.extend(rows.into_iter().map(format_row));
+
+    lines.join("\n")
+}
+
+/// Shows text through `$PAGER`, or `less` if it is not set, printing it
+/// directly when not running in a terminal or if the pager fails to start.
+pub(crate) fn page(text: &str) -> Result<()> {
+    if !stdout().is_terminal() {
+        println!("{}", text);
+        return Ok(());
+    }
+
+    let pager = env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+    let mut args = pager.split_whitespace();
+    let child = match args.next() {
+        Some(program) => Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn(),
+        None => {
+            println!("{}", text);
+            return Ok(());
+        }
+    };
+
+    match child {
+        Ok(mut child) => {
+            if let Some(stdin) = child.stdin.as_mut() {
+                // The pager may quit before reading everything
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            child.wait()?;
+        }
+        Err(_) => println!("{}", text),
+    }
+
+    Ok(())
+}
+
+/// Opens files in an editor, which may have arguments like `code --wait`,
+/// and waits for it to exit.
+pub(crate) fn edit(editor: &str, files: &[PathBuf]) -> Result<()> {
+    let mut args = editor.split_whitespace();
+    let program = args.next().ok_or(NoEditor)?;
+
+    let status = Command::new(program)
+        .args(args)
+        .args(files)
+        .status()
+        .map_err(|e| EditorError(editor.to_string(), e.to_string()))?;
+    if !status.success() {
+        return Err(EditorError(editor.to_string(), status.to_string()).into());
+    }
+
+    Ok(())
+}
+
+/// Opens a URL with `$BROWSER`, which may list several browsers separated by
+/// `:` and use `%s` for the URL, or the opener of the platform. Prints the
+/// URL instead when there is no browser, e.g. over SSH.
+pub(crate) fn open_url(url: &str) -> Result<()> {
+    let browsers: Vec<String> = match env::var("BROWSER") {
+        Ok(browsers) => browsers.split(':').map(str::to_string).collect(),
+        Err(_) if has_display() => vec![default_opener().to_string()],
+        Err(_) => Vec::new(),
+    };
+
+    for browser in browsers.iter().filter(|b| !b.trim().is_empty()) {
+        let mut args: Vec<String> = browser.split_whitespace().map(str::to_string).collect();
+        if args.iter().any(|a| a.contains("%s")) {
+            args = args.iter().map(|a| a.replace("%s", url)).collect();
+        } else {
+            args.push(url.to_string());
+        }
+
+        let status = Command::new(&args[0])
+            .args(&args[1..])
+            .stdin(Stdio::null())
+            .status();
+        if status.is_ok_and(|s| s.success()) {
+            return Ok(());
+        }
+    }
+
+    println!("{}", url);
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn default_opener() -> &'static str {
+    "open"
+}
+
+#[cfg(windows)]
+fn default_opener() -> &'static str {
+    "explorer"
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn default_opener() -> &'static str {
+    "xdg-open"
+}
+
+/// Whether a graphical browser can be started, which is not the case in
+/// e.g. SSH sessions on Linux.
+fn has_display() -> bool {
+    if cfg!(any(target_os = "macos", windows)) {
+        return env::var_os("SSH_CONNECTION").is_none();
+    }
+
+    env::var_os("DISPLAY").is_some() || env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_fuzzy_matches() {
+        let score = |candidate| fuzzy_score("two", candidate);
+
+        assert_eq!(score("TWO"), Some(FuzzyScore::exact()));
+        assert_eq!(score("twostones").unwrap().tier, MatchTier::Prefix);
+        assert_eq!(score("Take Two Stones").unwrap().tier, MatchTier::Substring);
+        assert_eq!(score("tweedledo").unwrap().tier, MatchTier::Subsequence);
+        assert_eq!(score("hello"), None);
+
+        let mut ranked = vec!["tweedledo", "twostones", "Take Two Stones", "two", "twos"];
+        ranked.sort_by_key(|c| std::cmp::Reverse(score(c)));
+        assert_eq!(
+            ranked,
+            vec!["two", "twos", "twostones", "Take Two Stones", "tweedledo"]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    languages::Languages,
+    layout::{Layout, LayoutConfig},
+    problem::Sample,
+    template::read_template,
+};
+use anyhow::Result;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use std::{
+    ffi::OsStr,
+    fs,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+pub(crate) const WORKSPACE_FILE: &str = "kah.toml";
+
+/// A project-local workspace, defined by a `kah.toml` file in the root of the
+/// workspace. Every path in it is relative to the directory the file is in.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub(crate) struct Workspace {
+    #[serde(skip)]
+    pub(crate) root: PathBuf,
+    /// Directory solutions are created in, defaults to the workspace root
+    pub(crate) code: Option<PathBuf>,
+    /// Language to create problems in without prompting
+    #[serde(deserialize_with = "deserialize_language")]
+    pub(crate) language: Option<Languages>,
+    /// Directory with solution templates, e.g. `Problem.rs`
+    pub(crate) templates: Option<PathBuf>,
+    /// Directory with extra test cases, in `<problem id>/<name>.{in,ans}`
+    pub(crate) tests: Option<PathBuf>,
+    /// Datafile for the workspace, defaults to `.kah/data.json`
+    pub(crate) data: Option<PathBuf>,
+    /// Where the files of new problems go, `flat` unless set
+    pub(crate) layout: Option<LayoutConfig>,
+    /// Create a cargo package for every Rust problem
+    pub(crate) cargo: Option<bool>,
+}
+
+impl Workspace {
+    /// Finds the closest workspace by walking up from `dir`.
+    pub(crate) fn find(dir: &Path) -> Result<Option<Workspace>> {
+        for dir in dir.ancestors() {
+            let file = dir.join(WORKSPACE_FILE);
+            if file.is_file() {
+                let mut workspace: Workspace = toml::from_str(&read_to_string(&file)?)?;
+                workspace.root = dir.to_owned();
+                return Ok(Some(workspace));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub(crate) fn code_dir(&self) -> PathBuf {
+        self.resolve(&self.code)
+            .unwrap_or_else(|| self.root.clone())
+    }
+
+    pub(crate) fn datafile(&self) -> PathBuf {
+        self.resolve(&self.data)
+            .unwrap_or_else(|| self.root.join(".kah").join("data.json"))
+    }
+
+    /// Returns the workspace template for a language, if there is one.
+    pub(crate) fn template(&self, language: &Languages) -> Result<Option<String>> {
+        match self.resolve(&self.templates) {
+            Some(dir) => read_template(&dir, language),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn layout(&self) -> Result<Layout> {
+        match &self.layout {
+            Some(layout) => layout.layout(),
+            None => Ok(Layout::default()),
+        }
+    }
+
+    /// Reads the extra test cases for a problem.
+    pub(crate) fn samples(&self, id: &str) -> Result<Vec<Sample>> {
+        match self.resolve(&self.tests) {
+            Some(dir) => read_samples(&dir.join(id)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn resolve(&self, path: &Option<PathBuf>) -> Option<PathBuf> {
+        path.as_ref().map(|p| self.root.join(p))
+    }
+}
+
+/// Reads the test cases in a directory, pairing `.in` and `.ans` files with
+/// the same name.
+pub(crate) fn read_samples(dir: &Path) -> Result<Vec<Sample>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut inputs: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some(OsStr::new("in")))
+        .collect();
+    inputs.sort_unstable();
+
+    let mut samples = Vec::new();
+    for input in inputs {
+        let answer = input.with_extension("ans");
+        if answer.is_file() {
+            samples.push(Sample {
+                input: read_to_string(&input)?,
+                expected: read_to_string(&answer)?,
+            });
+        }
+    }
+
+    Ok(samples)
+}
+
+/// Languages are written like on the command line, e.g. `rust` or `c++`.
+fn deserialize_language<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Languages>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|language| Languages::from_str(&language).map_err(D::Error::custom))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_languages() {
+        let workspace: Workspace = toml::from_str("language = \"c++\"").unwrap();
+        assert_eq!(workspace.language, Some(Languages::CPP));
+
+        let workspace: Workspace = toml::from_str("code = \"src\"").unwrap();
+        assert_eq!(workspace.language, None);
+
+        assert!(toml::from_str::<Workspace>("language = \"cobol\"").is_err());
+    }
+}